@@ -0,0 +1,120 @@
+//! Poll a real device for a long time and report on how reliable the connection was.
+//!
+//! Usage: cargo run --example soak -- [hours] [poll interval in seconds]
+
+use aranet::{AranetManager, ConnectionEvent, DeviceError, DisconnectReason, RetryPolicy};
+use futures::StreamExt;
+use std::{
+    env, fs,
+    time::{Duration, Instant},
+};
+
+#[derive(Default)]
+struct Report {
+    reads: u64,
+    disconnects: u64,
+    timeouts: u64,
+    decode_errors: u64,
+    other_errors: u64,
+    latencies: Vec<Duration>,
+}
+
+impl Report {
+    fn percentile(&self, p: f64) -> Duration {
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+
+        let index = ((latencies.len() as f64 - 1.0) * p).round() as usize;
+        latencies.get(index).copied().unwrap_or_default()
+    }
+
+    fn summary(&self, elapsed: Duration) -> String {
+        format!(
+            "elapsed: {:?}\nsuccessful reads: {}\ndisconnects: {}\ntimeouts: {}\ndecode errors: {}\nother errors: {}\nlatency p50: {:?}\nlatency p95: {:?}\nlatency p99: {:?}\n",
+            elapsed,
+            self.reads,
+            self.disconnects,
+            self.timeouts,
+            self.decode_errors,
+            self.other_errors,
+            self.percentile(0.50),
+            self.percentile(0.95),
+            self.percentile(0.99),
+        )
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let mut args = env::args().skip(1);
+    let hours = args
+        .next()
+        .and_then(|h| h.parse::<f64>().ok())
+        .unwrap_or(24.0);
+    let interval = args
+        .next()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(60);
+
+    let duration = Duration::from_secs_f64(hours * 3600.0);
+    let interval = Duration::from_secs(interval);
+
+    // Don't retry, so every failure shows up in the report
+    let manager = AranetManager::new().await.unwrap();
+    let device = manager
+        .connect()
+        .await
+        .unwrap()
+        .with_retry_policy(RetryPolicy::none());
+    // Reads reconnect transparently, so count the dropped connections as they happen instead
+    let mut events = Box::pin(device.connection_events(manager.adapter()).await.unwrap());
+    let mut report = Report::default();
+    let start = Instant::now();
+
+    while start.elapsed() < duration {
+        let read_start = Instant::now();
+        let result = tokio::time::timeout(Duration::from_secs(30), device.measurements()).await;
+
+        match result {
+            Ok(Ok(_)) => {
+                report.reads += 1;
+                report.latencies.push(read_start.elapsed());
+            }
+            Ok(Err(
+                DeviceError::Timeout { .. } | DeviceError::BTLE(btleplug::Error::TimedOut(_)),
+            ))
+            | Err(_) => {
+                eprintln!("[{:?}] timed out", start.elapsed());
+                report.timeouts += 1;
+            }
+            Ok(Err(err @ (DeviceError::IO(_) | DeviceError::UnexpectedPayload(_)))) => {
+                eprintln!("[{:?}] decode error: {err}", start.elapsed());
+                report.decode_errors += 1;
+            }
+            Ok(Err(err)) => {
+                eprintln!("[{:?}] error: {err}", start.elapsed());
+                report.other_errors += 1;
+            }
+        }
+
+        let sleep = tokio::time::sleep(interval);
+        tokio::pin!(sleep);
+
+        loop {
+            tokio::select! {
+                _ = &mut sleep => break,
+                Some(event) = events.next() => {
+                    if let ConnectionEvent::Disconnected { reason: DisconnectReason::Lost } = event {
+                        eprintln!("[{:?}] disconnected", start.elapsed());
+                        report.disconnects += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let summary = report.summary(start.elapsed());
+    println!("{summary}");
+
+    fs::write("soak-report.txt", summary).expect("Unable to write report");
+}
@@ -0,0 +1,76 @@
+use btleplug::{
+    api::{Central as _, Manager as _},
+    platform::{Adapter, Manager},
+};
+
+use crate::ConnectionError;
+
+/// Identifying information about a Bluetooth adapter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdapterInfo {
+    /// The position of the adapter in the list reported by the system
+    pub index: usize,
+    /// The platform's description of the adapter, like "hci0 (usb:v1D6Bp0246d0537)" on Linux
+    pub name: String,
+}
+
+/// Picks which Bluetooth adapter to use on hosts with more than one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterSelector {
+    /// The adapter at the given position in the list reported by the system
+    Index(usize),
+    /// The first adapter whose description contains the given text, like "hci1"
+    Name(String),
+}
+
+impl AdapterSelector {
+    fn matches(&self, info: &AdapterInfo) -> bool {
+        match self {
+            Self::Index(index) => info.index == *index,
+            Self::Name(name) => info.name.contains(name.as_str()),
+        }
+    }
+}
+
+impl Default for AdapterSelector {
+    fn default() -> Self {
+        Self::Index(0)
+    }
+}
+
+/// List the Bluetooth adapters available on the system
+pub async fn adapters() -> Result<Vec<AdapterInfo>, ConnectionError> {
+    Ok(all_adapters()
+        .await?
+        .into_iter()
+        .map(|(info, _)| info)
+        .collect())
+}
+
+/// Get the Bluetooth adapter picked by `selector`
+pub(crate) async fn select_adapter(selector: &AdapterSelector) -> Result<Adapter, ConnectionError> {
+    all_adapters()
+        .await?
+        .into_iter()
+        .find(|(info, _)| selector.matches(info))
+        .map(|(_, adapter)| adapter)
+        .ok_or(ConnectionError::AdapterUnavaliable)
+}
+
+/// Get every Bluetooth adapter available on the system
+pub(crate) async fn all_adapters() -> Result<Vec<(AdapterInfo, Adapter)>, ConnectionError> {
+    let manager = Manager::new().await?;
+
+    let adapters = manager
+        .adapters()
+        .await
+        .map_err(|_| ConnectionError::AdapterUnavaliable)?;
+
+    let mut infos = Vec::with_capacity(adapters.len());
+    for (index, adapter) in adapters.into_iter().enumerate() {
+        let name = adapter.adapter_info().await?;
+        infos.push((AdapterInfo { index, name }, adapter));
+    }
+
+    Ok(infos)
+}
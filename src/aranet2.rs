@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use btleplug::{api, platform::Peripheral};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::{
+    io::Cursor,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    history::{Parameter, StoredRecord},
+    Aranet4, Capabilities, ConnectionError, DeviceError, Info, Measurement, Sensor, Timeouts,
+    SENSOR_READINGS_CHARACTERISTIC,
+};
+
+/// Measurements from an Aranet2 device
+#[derive(Debug, Clone)]
+pub struct Aranet2Data {
+    /// Temperature in Celsius
+    pub temperature: f32,
+    /// Percentage of relative humidity
+    pub humidity: f32,
+    /// Percentage of battery remaining
+    pub battery: u8,
+    /// Measurement interval
+    pub interval: Duration,
+    /// Time since last update
+    pub since_last_update: Duration,
+}
+
+impl From<&Aranet2Data> for Vec<Measurement> {
+    fn from(data: &Aranet2Data) -> Self {
+        vec![
+            Measurement::Temperature(data.temperature),
+            Measurement::Humidity(data.humidity),
+            Measurement::Battery(data.battery),
+        ]
+    }
+}
+
+impl From<Aranet2Data> for Vec<Measurement> {
+    fn from(data: Aranet2Data) -> Self {
+        Self::from(&data)
+    }
+}
+
+/// A measurement stored on an Aranet2 device
+#[derive(Debug, Clone)]
+pub struct Aranet2Record {
+    /// When the measurement was taken, corrected for the drift of the device's clock
+    pub timestamp: SystemTime,
+    /// When the measurement was taken, assuming the device's clock keeps perfect time
+    pub raw_timestamp: SystemTime,
+    /// Temperature in Celsius
+    pub temperature: f32,
+    /// Percentage of relative humidity
+    pub humidity: f32,
+}
+
+impl StoredRecord for Aranet2Record {
+    const PARAMETERS: &'static [Parameter] = &[Parameter::Temperature, Parameter::Humidity2];
+
+    fn from_values(timestamp: SystemTime, raw_timestamp: SystemTime, values: &[u32]) -> Self {
+        Self {
+            timestamp,
+            raw_timestamp,
+            temperature: values[0] as f32 / 20.0,
+            humidity: values[1] as f32 / 10.0,
+        }
+    }
+}
+
+/// A connected Aranet2 temperature and humidity monitor
+pub struct Aranet2<P: api::Peripheral = Peripheral> {
+    device: Aranet4<P>,
+}
+
+impl<P: api::Peripheral> Aranet2<P> {
+    /// Wrap an already connected peripheral
+    pub fn new(device: P) -> Result<Self, ConnectionError> {
+        Ok(Self {
+            device: Aranet4::for_readings(device, SENSOR_READINGS_CHARACTERISTIC)?,
+        })
+    }
+
+    /// Abandon Bluetooth operations that take longer than the given timeouts
+    pub fn with_timeouts(self, timeouts: Timeouts) -> Self {
+        Self {
+            device: self.device.with_timeouts(timeouts),
+        }
+    }
+
+    /// Get the device information
+    pub async fn info(&self) -> Result<Info, DeviceError> {
+        self.device.info().await
+    }
+
+    /// Get the current measurements from the device
+    pub async fn measurements(&self) -> Result<Aranet2Data, DeviceError> {
+        let mut payload = Cursor::new(self.device.read(SENSOR_READINGS_CHARACTERISTIC).await?);
+
+        let _device_type = payload.read_u16::<LittleEndian>()?;
+        let interval = payload.read_u16::<LittleEndian>()?;
+        let since_last_update = payload.read_u16::<LittleEndian>()?;
+        let battery = payload.read_u8()?;
+        let temperature = f32::from(payload.read_u16::<LittleEndian>()?) / 20.0;
+        let humidity = f32::from(payload.read_u16::<LittleEndian>()?) / 10.0;
+
+        Ok(Aranet2Data {
+            temperature,
+            humidity,
+            battery,
+            interval: Duration::from_secs(u64::from(interval)),
+            since_last_update: Duration::from_secs(u64::from(since_last_update)),
+        })
+    }
+
+    /// Download all the measurements stored on the device
+    pub async fn history(&self) -> Result<Vec<Aranet2Record>, DeviceError> {
+        self.device.stored_history().await
+    }
+
+    /// Detect the features supported by the device
+    pub async fn capabilities(&self) -> Result<Capabilities, DeviceError> {
+        self.device.capabilities().await
+    }
+
+    /// Disconnect from the device
+    pub async fn disconnect(&self) -> Result<(), DeviceError> {
+        self.device.disconnect().await
+    }
+}
+
+#[async_trait]
+impl<P: api::Peripheral> Sensor for Aranet2<P> {
+    type Reading = Aranet2Data;
+    type Record = Aranet2Record;
+
+    async fn info(&self) -> Result<Info, DeviceError> {
+        Aranet2::info(self).await
+    }
+
+    async fn read(&self) -> Result<Aranet2Data, DeviceError> {
+        self.measurements().await
+    }
+
+    async fn history(&self) -> Result<Vec<Aranet2Record>, DeviceError> {
+        Aranet2::history(self).await
+    }
+
+    async fn capabilities(&self) -> Result<Capabilities, DeviceError> {
+        Aranet2::capabilities(self).await
+    }
+}
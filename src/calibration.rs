@@ -0,0 +1,25 @@
+use btleplug::api::Peripheral;
+use uuid::{uuid, Uuid};
+
+use crate::{Aranet4, DeviceError};
+
+pub(crate) const CALIBRATION_CHARACTERISTIC: Uuid = uuid!("f0cd1502-95da-4f4b-9ac8-aa55d312af0c");
+
+/// Calibration data reported by the device
+///
+/// The layout of this data is not publicly documented, so it is exposed as-is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Calibration {
+    /// The raw calibration data
+    pub data: Vec<u8>,
+}
+
+impl<P: Peripheral> Aranet4<P> {
+    /// Get the calibration data stored on the device
+    pub async fn calibration(&self) -> Result<Calibration, DeviceError> {
+        self.require(CALIBRATION_CHARACTERISTIC, "calibration")?;
+        let data = self.read(CALIBRATION_CHARACTERISTIC).await?;
+
+        Ok(Calibration { data })
+    }
+}
@@ -0,0 +1,59 @@
+use btleplug::api::Peripheral;
+use uuid::Uuid;
+
+use crate::{
+    calibration::CALIBRATION_CHARACTERISTIC,
+    history::{HISTORY_V1_CHARACTERISTIC, HISTORY_V2_CHARACTERISTIC},
+    settings::SETTINGS_CHARACTERISTIC,
+    Aranet4, DeviceError, Version, DETAILED_READINGS_CHARACTERISTIC,
+};
+
+/// Firmware version that introduced Smart Home integration
+const SMART_HOME_INTEGRATION_VERSION: Version = Version::new(1, 2, 0);
+
+/// The features supported by a device, detected from its firmware version and GATT layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The device's firmware version, if it could be parsed
+    pub firmware_version: Option<Version>,
+    /// Whether the stored history can be downloaded
+    pub history: bool,
+    /// Whether the history can be downloaded with the faster read-based protocol
+    pub history_v2: bool,
+    /// Whether higher resolution readings are available
+    pub detailed_readings: bool,
+    /// Whether the settings can be read
+    pub settings: bool,
+    /// Whether the calibration data can be read
+    pub calibration: bool,
+    /// Whether readings can be included in Bluetooth advertisements
+    pub smart_home_integration: bool,
+}
+
+impl<P: Peripheral> Aranet4<P> {
+    /// Detect the features supported by the device
+    pub async fn capabilities(&self) -> Result<Capabilities, DeviceError> {
+        let firmware_version = self.info().await?.firmware_version;
+        let has = |uuid| self.characteristic(uuid).is_ok();
+
+        Ok(Capabilities {
+            history: has(HISTORY_V1_CHARACTERISTIC) || has(HISTORY_V2_CHARACTERISTIC),
+            history_v2: has(HISTORY_V2_CHARACTERISTIC),
+            detailed_readings: has(DETAILED_READINGS_CHARACTERISTIC),
+            settings: has(SETTINGS_CHARACTERISTIC),
+            calibration: has(CALIBRATION_CHARACTERISTIC),
+            smart_home_integration: firmware_version
+                .as_ref()
+                .is_some_and(|version| *version >= SMART_HOME_INTEGRATION_VERSION),
+            firmware_version,
+        })
+    }
+
+    /// Fail with [`DeviceError::Unsupported`] if the device doesn't expose the characteristic
+    /// backing `feature`
+    pub(crate) fn require(&self, uuid: Uuid, feature: &'static str) -> Result<(), DeviceError> {
+        self.characteristic(uuid)
+            .map(|_| ())
+            .map_err(|_| DeviceError::Unsupported(feature))
+    }
+}
@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use btleplug::api::Peripheral;
+
+use crate::{Aranet4, DeviceError, HistoryRecord, Info, SensorData};
+
+/// An object-safe interface to an Aranet4, so it can be injected as a `Box<dyn AranetClient>`
+#[async_trait]
+pub trait AranetClient: Send + Sync {
+    /// Get the device information
+    async fn info(&self) -> Result<Info, DeviceError>;
+
+    /// Get the current measurements from the device
+    async fn measurements(&self) -> Result<SensorData, DeviceError>;
+
+    /// Download the measurements stored on the device
+    async fn history(&self) -> Result<Vec<HistoryRecord>, DeviceError>;
+}
+
+#[async_trait]
+impl<P: Peripheral> AranetClient for Aranet4<P> {
+    async fn info(&self) -> Result<Info, DeviceError> {
+        Aranet4::info(self).await
+    }
+
+    async fn measurements(&self) -> Result<SensorData, DeviceError> {
+        Aranet4::measurements(self).await
+    }
+
+    async fn history(&self) -> Result<Vec<HistoryRecord>, DeviceError> {
+        Aranet4::history(self).await
+    }
+}
+
+/// A fake client that always returns the same values, useful for testing
+#[derive(Debug, Clone)]
+pub struct MockClient {
+    /// The device information to return
+    pub info: Info,
+    /// The measurements to return
+    pub measurements: SensorData,
+    /// The history to return
+    pub history: Vec<HistoryRecord>,
+}
+
+impl MockClient {
+    /// Box the client as a trait object
+    pub fn boxed(self) -> Box<dyn AranetClient> {
+        Box::new(self)
+    }
+}
+
+#[async_trait]
+impl AranetClient for MockClient {
+    async fn info(&self) -> Result<Info, DeviceError> {
+        Ok(self.info.clone())
+    }
+
+    async fn measurements(&self) -> Result<SensorData, DeviceError> {
+        Ok(self.measurements.clone())
+    }
+
+    async fn history(&self) -> Result<Vec<HistoryRecord>, DeviceError> {
+        Ok(self.history.clone())
+    }
+}
@@ -0,0 +1,113 @@
+use btleplug::{
+    api::{Central as _, CentralEvent, Peripheral},
+    platform::Adapter,
+};
+use futures::{stream, Stream, StreamExt};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{Aranet4, DeviceError};
+
+/// A change in the state of the connection to a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// The device connected
+    Connected,
+    /// The device disconnected
+    Disconnected {
+        /// Why the connection ended
+        reason: DisconnectReason,
+    },
+    /// A reconnection attempt started
+    Reconnecting,
+}
+
+/// Why the connection to a device ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The handle was asked to disconnect
+    Requested,
+    /// The connection dropped, like when the device went out of range
+    Lost,
+}
+
+/// Tracks the events raised by the handle itself, as opposed to those reported by the adapter
+#[derive(Debug)]
+pub(crate) struct ConnectionMonitor {
+    events: broadcast::Sender<ConnectionEvent>,
+    disconnecting: Arc<AtomicBool>,
+}
+
+impl ConnectionMonitor {
+    /// Note that the handle is about to disconnect on purpose
+    pub(crate) fn disconnecting(&self) {
+        self.disconnecting.store(true, Ordering::Relaxed);
+    }
+
+    /// Note that the handle is about to reconnect
+    pub(crate) fn reconnecting(&self) {
+        self.disconnecting.store(false, Ordering::Relaxed);
+        let _ = self.events.send(ConnectionEvent::Reconnecting);
+    }
+}
+
+impl Default for ConnectionMonitor {
+    fn default() -> Self {
+        Self {
+            events: broadcast::channel(16).0,
+            disconnecting: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl<P: Peripheral> Aranet4<P> {
+    /// Get a stream of changes to the state of the connection, so the application can react to
+    /// the device going out of range as it happens
+    ///
+    /// The adapter must be the one the device was connected through.
+    pub async fn connection_events(
+        &self,
+        adapter: &Adapter,
+    ) -> Result<impl Stream<Item = ConnectionEvent>, DeviceError> {
+        let id = self.device.id();
+        let disconnecting = self.connection.disconnecting.clone();
+
+        let central = adapter.events().await?.filter_map(move |event| {
+            let event = match event {
+                CentralEvent::DeviceConnected(device) if device == id => {
+                    Some(ConnectionEvent::Connected)
+                }
+                CentralEvent::DeviceDisconnected(device) if device == id => {
+                    let reason = if disconnecting.load(Ordering::Relaxed) {
+                        DisconnectReason::Requested
+                    } else {
+                        DisconnectReason::Lost
+                    };
+
+                    Some(ConnectionEvent::Disconnected { reason })
+                }
+                _ => None,
+            };
+
+            async move { event }
+        });
+
+        let own = stream::unfold(
+            self.connection.events.subscribe(),
+            |mut events| async move {
+                loop {
+                    match events.recv().await {
+                        Ok(event) => return Some((event, events)),
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            },
+        );
+
+        Ok(stream::select(central, own))
+    }
+}
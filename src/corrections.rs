@@ -0,0 +1,55 @@
+use crate::SensorData;
+
+/// A linear correction applied to a measured value, as `value * scale + offset`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Correction {
+    /// Factor the raw value is multiplied by
+    pub scale: f32,
+    /// Amount added to the scaled value
+    pub offset: f32,
+}
+
+impl Correction {
+    /// A correction that only shifts the value
+    pub const fn offset(offset: f32) -> Self {
+        Self { scale: 1.0, offset }
+    }
+
+    /// Apply the correction to a value
+    pub fn apply(&self, value: f32) -> f32 {
+        value * self.scale + self.offset
+    }
+}
+
+impl Default for Correction {
+    fn default() -> Self {
+        Self::offset(0.0)
+    }
+}
+
+/// Per-metric corrections for a device, e.g. when placement makes it read 0.8 °C high
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Corrections {
+    /// Correction for the CO2 concentration, in ppm
+    pub co2: Correction,
+    /// Correction for the temperature, in Celsius
+    pub temperature: Correction,
+    /// Correction for the relative humidity, in percentage points
+    pub humidity: Correction,
+    /// Correction for the atmospheric pressure, in hPa
+    pub pressure: Correction,
+}
+
+impl Corrections {
+    /// Apply the corrections to a reading
+    pub fn apply(&self, mut data: SensorData) -> SensorData {
+        data.co2 = self.co2.apply(f32::from(data.co2)).round() as u16;
+        data.temperature = self.temperature.apply(data.temperature);
+        let humidity = self.humidity.apply(data.humidity_percent());
+        data.humidity = humidity.round() as u8;
+        data.humidity_precise = data.humidity_precise.map(|_| humidity);
+        data.pressure = self.pressure.apply(f32::from(data.pressure)).round() as u16;
+
+        data
+    }
+}
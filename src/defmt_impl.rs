@@ -0,0 +1,110 @@
+use defmt::{write, Format, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    Aranet2Record, HistoryRecord, RadiationRecord, RadonRecord, SensorData, SensorState, Status,
+};
+
+impl Format for Status {
+    fn format(&self, f: Formatter) {
+        match self {
+            Status::NONE => write!(f, "NONE"),
+            Status::GREEN => write!(f, "GREEN"),
+            Status::AMBER => write!(f, "AMBER"),
+            Status::RED => write!(f, "RED"),
+        }
+    }
+}
+
+impl Format for SensorState {
+    fn format(&self, f: Formatter) {
+        match self {
+            SensorState::Ready => write!(f, "Ready"),
+            SensorState::WarmingUp => write!(f, "WarmingUp"),
+            SensorState::Invalid => write!(f, "Invalid"),
+        }
+    }
+}
+
+impl Format for SensorData {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "SensorData {{ co2: {=u16}, status: {}, sensor_state: {}, battery: {=u8}, humidity: {=u8}, humidity_precise: {}, pressure: {=u16}, temperature: {=f32}, interval: {=u64}s, since_last_update: {=u64}s, counter: {} }}",
+            self.co2,
+            self.status,
+            self.sensor_state,
+            self.battery,
+            self.humidity,
+            self.humidity_precise,
+            self.pressure,
+            self.temperature,
+            self.interval.as_secs(),
+            self.since_last_update.as_secs(),
+            self.counter,
+        )
+    }
+}
+
+impl Format for HistoryRecord {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "HistoryRecord {{ timestamp: {=u64}s, raw_timestamp: {=u64}s, co2: {=u16}, temperature: {=f32}, humidity: {=u8}, pressure: {=u16} }}",
+            unix_seconds(self.timestamp),
+            unix_seconds(self.raw_timestamp),
+            self.co2,
+            self.temperature,
+            self.humidity,
+            self.pressure,
+        )
+    }
+}
+
+impl Format for Aranet2Record {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "Aranet2Record {{ timestamp: {=u64}s, raw_timestamp: {=u64}s, temperature: {=f32}, humidity: {=f32} }}",
+            unix_seconds(self.timestamp),
+            unix_seconds(self.raw_timestamp),
+            self.temperature,
+            self.humidity,
+        )
+    }
+}
+
+impl Format for RadonRecord {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "RadonRecord {{ timestamp: {=u64}s, raw_timestamp: {=u64}s, radon: {=u32}, temperature: {=f32}, humidity: {=f32}, pressure: {=u16} }}",
+            unix_seconds(self.timestamp),
+            unix_seconds(self.raw_timestamp),
+            self.radon,
+            self.temperature,
+            self.humidity,
+            self.pressure,
+        )
+    }
+}
+
+impl Format for RadiationRecord {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "RadiationRecord {{ timestamp: {=u64}s, raw_timestamp: {=u64}s, dose_rate: {=f32}, dose: {=f32} }}",
+            unix_seconds(self.timestamp),
+            unix_seconds(self.raw_timestamp),
+            self.dose_rate,
+            self.dose,
+        )
+    }
+}
+
+/// Timestamps are logged as seconds since the Unix epoch, as defmt has no notion of wall time
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
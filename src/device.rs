@@ -0,0 +1,126 @@
+use btleplug::{api, platform::Peripheral};
+
+use crate::{
+    with_timeout, Aranet2, Aranet4, Capabilities, ConnectionError, DeviceError, DeviceKind, Info,
+    Measurement, Radiation, Radon, Sensor, Timeouts, MODEL_NUMBER_CHARACTERISTIC,
+};
+
+/// A connected Aranet device of any supported model
+///
+/// This is the entry point for handling a fleet of mixed models through a single type, since
+/// [`Sensor`] can't be used as a trait object.
+pub enum AranetDevice<P: api::Peripheral = Peripheral> {
+    /// An Aranet4 CO2 monitor
+    Aranet4(Aranet4<P>),
+    /// An Aranet2 temperature and humidity monitor
+    Aranet2(Aranet2<P>),
+    /// An Aranet Radon Plus monitor
+    Radon(Radon<P>),
+    /// An Aranet Radiation monitor
+    Radiation(Radiation<P>),
+}
+
+impl<P: api::Peripheral> AranetDevice<P> {
+    /// Wrap an already connected peripheral, detecting its model from its advertised name, or
+    /// from its model number if it doesn't advertise one
+    pub async fn new(device: P) -> Result<Self, ConnectionError> {
+        Self::new_with_timeouts(device, Timeouts::default()).await
+    }
+
+    /// Like [`AranetDevice::new`], abandoning the model detection and every later operation on
+    /// the handle once they exceed the given timeouts
+    pub async fn new_with_timeouts(device: P, timeouts: Timeouts) -> Result<Self, ConnectionError> {
+        match detect_kind(&device, &timeouts).await? {
+            DeviceKind::Aranet4 => Ok(Self::Aranet4(Aranet4::new(device)?.with_timeouts(timeouts))),
+            DeviceKind::Aranet2 => Ok(Self::Aranet2(Aranet2::new(device)?.with_timeouts(timeouts))),
+            DeviceKind::Radon => Ok(Self::Radon(Radon::new(device)?.with_timeouts(timeouts))),
+            DeviceKind::Radiation => Ok(Self::Radiation(
+                Radiation::new(device)?.with_timeouts(timeouts),
+            )),
+            DeviceKind::Unknown => Err(ConnectionError::UnsupportedDevice),
+        }
+    }
+
+    /// The kind of device
+    pub fn kind(&self) -> DeviceKind {
+        match self {
+            Self::Aranet4(_) => DeviceKind::Aranet4,
+            Self::Aranet2(_) => DeviceKind::Aranet2,
+            Self::Radon(_) => DeviceKind::Radon,
+            Self::Radiation(_) => DeviceKind::Radiation,
+        }
+    }
+
+    /// Get the device information
+    pub async fn info(&self) -> Result<Info, DeviceError> {
+        match self {
+            Self::Aranet4(device) => device.info().await,
+            Self::Aranet2(device) => device.info().await,
+            Self::Radon(device) => device.info().await,
+            Self::Radiation(device) => device.info().await,
+        }
+    }
+
+    /// Get the current readings from the device as a list of measurements
+    pub async fn read_measurements(&self) -> Result<Vec<Measurement>, DeviceError> {
+        match self {
+            Self::Aranet4(device) => device.read_measurements().await,
+            Self::Aranet2(device) => device.read_measurements().await,
+            Self::Radon(device) => device.read_measurements().await,
+            Self::Radiation(device) => device.read_measurements().await,
+        }
+    }
+
+    /// Detect the features supported by the device
+    pub async fn capabilities(&self) -> Result<Capabilities, DeviceError> {
+        match self {
+            Self::Aranet4(device) => device.capabilities().await,
+            Self::Aranet2(device) => device.capabilities().await,
+            Self::Radon(device) => device.capabilities().await,
+            Self::Radiation(device) => device.capabilities().await,
+        }
+    }
+
+    /// Disconnect from the device
+    pub async fn disconnect(&self) -> Result<(), DeviceError> {
+        match self {
+            Self::Aranet4(device) => device.disconnect().await,
+            Self::Aranet2(device) => device.disconnect().await,
+            Self::Radon(device) => device.disconnect().await,
+            Self::Radiation(device) => device.disconnect().await,
+        }
+    }
+}
+
+async fn detect_kind<P: api::Peripheral>(
+    device: &P,
+    timeouts: &Timeouts,
+) -> Result<DeviceKind, ConnectionError> {
+    let name = device
+        .properties()
+        .await?
+        .and_then(|properties| properties.local_name);
+
+    if let Some(kind) = name
+        .map(|name| DeviceKind::from_name(&name))
+        .filter(|kind| *kind != DeviceKind::Unknown)
+    {
+        return Ok(kind);
+    }
+
+    let Some(characteristic) = device
+        .characteristics()
+        .into_iter()
+        .find(|c| c.uuid == MODEL_NUMBER_CHARACTERISTIC)
+    else {
+        return Ok(DeviceKind::Unknown);
+    };
+
+    let model_number =
+        with_timeout::<_, ConnectionError>("read", timeouts.read, device.read(&characteristic))
+            .await?;
+
+    Ok(DeviceKind::from_name(&String::from_utf8_lossy(
+        &model_number,
+    )))
+}
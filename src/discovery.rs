@@ -0,0 +1,309 @@
+use btleplug::{
+    api::{BDAddr, Central as _, CentralEvent, Peripheral as _, PeripheralProperties},
+    platform::{Adapter, Peripheral},
+};
+use futures::{
+    stream::{self, FuturesUnordered},
+    Stream, StreamExt,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    connect_peripheral, estimate_distance, Aranet4, AranetDevice, ConnectionError, DeviceKind,
+    ScanMode, Timeouts,
+};
+
+/// An Aranet device found while scanning
+#[derive(Debug, Clone)]
+pub struct DiscoveredDevice {
+    /// The underlying btleplug peripheral
+    pub peripheral: Peripheral,
+    /// The device's advertised name
+    pub name: String,
+    /// The kind of device, detected from its name
+    pub kind: DeviceKind,
+    /// The device's Bluetooth address
+    pub address: BDAddr,
+    /// Signal strength of the device in dBm, if reported by the adapter
+    pub rssi: Option<i16>,
+    /// Transmission power advertised by the device in dBm, if any
+    pub tx_power: Option<i16>,
+    /// Estimated distance to the device in meters, if it advertises its transmission power
+    pub distance: Option<f32>,
+    /// Manufacturer-specific advertisement data, keyed by company identifier
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+}
+
+impl DiscoveredDevice {
+    pub(crate) fn new(peripheral: Peripheral, properties: PeripheralProperties) -> Self {
+        let name = properties.local_name.unwrap_or_default();
+
+        Self {
+            peripheral,
+            kind: DeviceKind::from_name(&name),
+            name,
+            address: properties.address,
+            rssi: properties.rssi,
+            tx_power: properties.tx_power_level,
+            distance: properties
+                .rssi
+                .zip(properties.tx_power_level)
+                .map(|(rssi, tx_power)| estimate_distance(f32::from(rssi), tx_power)),
+            manufacturer_data: properties.manufacturer_data,
+        }
+    }
+
+    /// Connect to the device as an Aranet4
+    pub async fn connect(self) -> Result<Aranet4, ConnectionError> {
+        self.connect_with_timeouts(Timeouts::default()).await
+    }
+
+    /// Connect to the device as an Aranet4, using the given timeouts for the connection and
+    /// every later operation on the handle
+    pub async fn connect_with_timeouts(
+        self,
+        timeouts: Timeouts,
+    ) -> Result<Aranet4, ConnectionError> {
+        Aranet4::from_peripheral_with_timeouts(self.peripheral, timeouts).await
+    }
+
+    /// Connect to the device, whatever its model
+    pub async fn connect_any(self) -> Result<AranetDevice, ConnectionError> {
+        self.connect_any_with_timeouts(Timeouts::default()).await
+    }
+
+    /// Connect to the device whatever its model, using the given timeouts for the connection and
+    /// every later operation on the handle
+    pub async fn connect_any_with_timeouts(
+        self,
+        timeouts: Timeouts,
+    ) -> Result<AranetDevice, ConnectionError> {
+        connect_peripheral(&self.peripheral, &timeouts).await?;
+
+        AranetDevice::new_with_timeouts(self.peripheral, timeouts).await
+    }
+}
+
+/// A change in the set of Aranet devices in range
+#[derive(Debug, Clone)]
+pub enum DiscoveryEvent {
+    /// A device started advertising
+    Appeared(DiscoveredDevice),
+    /// A device stopped advertising, as last seen
+    Disappeared(DiscoveredDevice),
+}
+
+/// Scan for Aranet devices of any supported model for the given duration, returning every one
+/// that was seen
+pub async fn scan(
+    adapter: &Adapter,
+    duration: Duration,
+    scan_mode: ScanMode,
+) -> Result<Vec<DiscoveredDevice>, ConnectionError> {
+    adapter.start_scan(scan_mode.filter()).await?;
+
+    tokio::time::sleep(duration).await;
+    let devices = known_devices(adapter).await;
+
+    adapter.stop_scan().await?;
+
+    Ok(devices?)
+}
+
+/// Scan for Aranet devices of any supported model without a deadline, yielding each one as it is first seen
+///
+/// The stream never ends on its own, except after an adapter error. Call [`ScanStream::stop`]
+/// once you have found the devices you were looking for.
+pub async fn discover(
+    adapter: &Adapter,
+    scan_mode: ScanMode,
+) -> Result<
+    ScanStream<impl Stream<Item = Result<DiscoveredDevice, ConnectionError>>>,
+    ConnectionError,
+> {
+    adapter.start_scan(scan_mode.filter()).await?;
+
+    let peripherals = Box::pin(seen_peripherals(adapter.clone()).await?);
+
+    let devices = stream::unfold(
+        (peripherals, HashSet::new()),
+        |(mut peripherals, mut seen)| async move {
+            while let Some(found) = peripherals.next().await {
+                if seen.contains(&found.0.id()) {
+                    continue;
+                }
+
+                if let Some(device) = identify(found) {
+                    seen.insert(device.peripheral.id());
+                    return Some((Ok(device), (peripherals, seen)));
+                }
+            }
+
+            None
+        },
+    );
+
+    Ok(ScanStream::new(adapter.clone(), devices))
+}
+
+/// Scan for Aranet devices of any supported model without a deadline, reporting each one as it
+/// comes into range and again once it hasn't advertised for `stale_after`
+///
+/// Meant for long-running gateways that need to notice sensors being installed or removed. The
+/// stream only ends if the adapter goes away; call [`ScanStream::stop`] to stop the scan.
+pub async fn watch_devices(
+    adapter: &Adapter,
+    stale_after: Duration,
+    scan_mode: ScanMode,
+) -> Result<ScanStream<impl Stream<Item = DiscoveryEvent>>, ConnectionError> {
+    adapter.start_scan(scan_mode.filter()).await?;
+
+    let peripherals = Box::pin(seen_peripherals(adapter.clone()).await?);
+    let ticks = tokio::time::interval(Duration::from_secs(1));
+
+    let state = (peripherals, ticks, HashMap::new(), VecDeque::new());
+
+    let events = stream::unfold(state, move |state| async move {
+        let (mut peripherals, mut ticks, mut present, mut pending) = state;
+
+        loop {
+            if let Some(event) = pending.pop_front() {
+                return Some((event, (peripherals, ticks, present, pending)));
+            }
+
+            tokio::select! {
+                found = peripherals.next() => {
+                    let found = found?;
+
+                    if let Some((_, last_seen)) = present.get_mut(&found.0.id()) {
+                        *last_seen = Instant::now();
+                    } else if let Some(device) = identify(found) {
+                        present.insert(device.peripheral.id(), (device.clone(), Instant::now()));
+                        pending.push_back(DiscoveryEvent::Appeared(device));
+                    }
+                }
+                _ = ticks.tick() => {
+                    let stale = present
+                        .iter()
+                        .filter(|(_, (_, last_seen))| last_seen.elapsed() >= stale_after)
+                        .map(|(id, _)| id.clone())
+                        .collect::<Vec<_>>();
+
+                    for id in stale {
+                        if let Some((device, _)) = present.remove(&id) {
+                            pending.push_back(DiscoveryEvent::Disappeared(device));
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(ScanStream::new(adapter.clone(), events))
+}
+
+/// Every peripheral the adapter already knows about, followed by each one it discovers or gets
+/// a new advertisement from, along with its properties
+///
+/// The properties of the known peripherals are looked up concurrently, and peripherals without
+/// any are skipped.
+pub(crate) async fn seen_peripherals(
+    adapter: Adapter,
+) -> Result<impl Stream<Item = (Peripheral, PeripheralProperties)> + Send + 'static, btleplug::Error>
+{
+    // Subscribe before listing the known peripherals, so none are missed in between
+    let events = adapter.events().await?;
+    let known = adapter.peripherals().await?;
+
+    let lookups = known.len().max(1);
+    let known = stream::iter(known)
+        .map(with_properties)
+        .buffer_unordered(lookups)
+        .filter_map(std::future::ready);
+
+    let updates = events.filter_map(move |event| {
+        let adapter = adapter.clone();
+
+        async move {
+            match event {
+                CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) => {
+                    with_properties(adapter.peripheral(&id).await.ok()?).await
+                }
+                _ => None,
+            }
+        }
+    });
+
+    Ok(known.chain(updates))
+}
+
+/// Look up the peripheral's properties, if it has any
+pub(crate) async fn with_properties(
+    peripheral: Peripheral,
+) -> Option<(Peripheral, PeripheralProperties)> {
+    let properties = peripheral.properties().await.ok()??;
+
+    Some((peripheral, properties))
+}
+
+/// A scan that keeps running until [`ScanStream::stop`] is called, yielding what it finds
+///
+/// Dropping the stream leaves the adapter scanning, as the crate spawns no background tasks to
+/// stop it.
+pub struct ScanStream<S> {
+    adapter: Adapter,
+    stream: Pin<Box<S>>,
+}
+
+impl<S: Stream> ScanStream<S> {
+    fn new(adapter: Adapter, stream: S) -> Self {
+        Self {
+            adapter,
+            stream: Box::pin(stream),
+        }
+    }
+
+    /// Stop the scan on the adapter
+    pub async fn stop(self) -> Result<(), ConnectionError> {
+        self.adapter.stop_scan().await?;
+
+        Ok(())
+    }
+}
+
+impl<S: Stream> Stream for ScanStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream.as_mut().poll_next(cx)
+    }
+}
+
+/// Every supported Aranet device the adapter has seen so far
+pub(crate) async fn known_devices(
+    adapter: &Adapter,
+) -> Result<Vec<DiscoveredDevice>, btleplug::Error> {
+    Ok(adapter
+        .peripherals()
+        .await?
+        .into_iter()
+        .map(with_properties)
+        .collect::<FuturesUnordered<_>>()
+        .filter_map(|found| std::future::ready(found.and_then(identify)))
+        .collect()
+        .await)
+}
+
+/// Describe the peripheral, if it is a supported Aranet device
+fn identify(
+    (peripheral, properties): (Peripheral, PeripheralProperties),
+) -> Option<DiscoveredDevice> {
+    let device = DiscoveredDevice::new(peripheral, properties);
+
+    (device.kind != DeviceKind::Unknown).then_some(device)
+}
@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use std::time::SystemTime;
+
+use crate::SensorData;
+
+/// A reading tagged with the device it came from and when it was taken
+#[derive(Debug, Clone)]
+pub struct TaggedReading {
+    /// A stable identifier for the device, like its serial number or an alias
+    pub device: String,
+    /// When the reading was taken
+    pub timestamp: SystemTime,
+    /// The reading itself
+    pub data: SensorData,
+}
+
+/// Errors that can occur when exporting readings
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Other(#[from] Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// A destination for readings, like a file, a database or a message broker
+#[async_trait]
+pub trait Exporter: Send {
+    /// Export a batch of readings
+    async fn export(&mut self, readings: &[TaggedReading]) -> Result<(), ExportError>;
+
+    /// Make sure all exported readings have been written out
+    async fn flush(&mut self) -> Result<(), ExportError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Exporter for Vec<Box<dyn Exporter>> {
+    async fn export(&mut self, readings: &[TaggedReading]) -> Result<(), ExportError> {
+        for exporter in self.iter_mut() {
+            exporter.export(readings).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), ExportError> {
+        for exporter in self.iter_mut() {
+            exporter.flush().await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use async_trait::async_trait;
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::{ExportError, Exporter, TaggedReading};
+
+/// The format readings are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// One JSON object per line
+    JsonLines,
+    /// Comma-separated values, with a header line
+    Csv,
+}
+
+/// When to start a new file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// Keep appending to the same file
+    Never,
+    /// Rotate once the file grows past the given number of bytes
+    Size(u64),
+    /// Rotate when the (UTC) day changes
+    Daily,
+}
+
+/// Appends readings to a file, rotating and pruning old files as configured
+///
+/// Rotated files get a numeric suffix (`aranet.log.1` being the most recent), and only the
+/// configured number of them is kept.
+#[derive(Debug)]
+pub struct FileExporter {
+    path: PathBuf,
+    format: FileFormat,
+    rotation: Rotation,
+    retention: usize,
+    file: Option<File>,
+    size: u64,
+    day: u64,
+}
+
+impl FileExporter {
+    /// Create an exporter writing to the given path, without rotation
+    pub fn new(path: impl Into<PathBuf>, format: FileFormat) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            rotation: Rotation::Never,
+            retention: 7,
+            file: None,
+            size: 0,
+            day: today(),
+        }
+    }
+
+    /// Set when to start a new file
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Set how many rotated files to keep around
+    pub fn with_retention(mut self, files: usize) -> Self {
+        self.retention = files;
+        self
+    }
+
+    async fn open(&mut self) -> Result<&mut File, ExportError> {
+        if self.file.is_none() {
+            // Pick up the state of a file left by an earlier run, so one from a previous day is
+            // rotated instead of appended to
+            if let Ok(metadata) = fs::metadata(&self.path).await {
+                self.size = metadata.len();
+                self.day = metadata.modified().map_or_else(|_| today(), day);
+            }
+        }
+
+        let needs_rotation = match self.rotation {
+            Rotation::Never => false,
+            Rotation::Size(max) => self.size >= max,
+            Rotation::Daily => self.day != today(),
+        };
+
+        if needs_rotation {
+            self.file = None;
+            self.rotate().await?;
+        }
+
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await?;
+
+            self.size = file.metadata().await?.len();
+            self.day = today();
+
+            let file = self.file.insert(file);
+            if self.size == 0 && self.format == FileFormat::Csv {
+                let header = "device,timestamp,co2,temperature,humidity,pressure,battery,status\n";
+                file.write_all(header.as_bytes()).await?;
+                self.size += header.len() as u64;
+            }
+        }
+
+        Ok(self.file.as_mut().expect("the file was just opened"))
+    }
+
+    async fn rotate(&self) -> Result<(), ExportError> {
+        if !fs::try_exists(&self.path).await? {
+            return Ok(());
+        }
+
+        if self.retention == 0 {
+            fs::remove_file(&self.path).await?;
+            return Ok(());
+        }
+
+        let oldest = rotated(&self.path, self.retention);
+        if fs::try_exists(&oldest).await? {
+            fs::remove_file(oldest).await?;
+        }
+
+        for n in (1..self.retention).rev() {
+            let from = rotated(&self.path, n);
+            if fs::try_exists(&from).await? {
+                fs::rename(from, rotated(&self.path, n + 1)).await?;
+            }
+        }
+
+        fs::rename(&self.path, rotated(&self.path, 1)).await?;
+
+        Ok(())
+    }
+
+    fn format(&self, reading: &TaggedReading) -> String {
+        let timestamp = reading
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let data = &reading.data;
+
+        match self.format {
+            FileFormat::JsonLines => format!(
+                "{{\"device\":\"{}\",\"timestamp\":{timestamp},\"co2\":{},\"temperature\":{},\"humidity\":{},\"pressure\":{},\"battery\":{},\"status\":\"{:?}\"}}\n",
+                escape_json(&reading.device),
+                data.co2,
+                data.temperature,
+                data.humidity_percent(),
+                data.pressure,
+                data.battery,
+                data.status,
+            ),
+            FileFormat::Csv => format!(
+                "{},{timestamp},{},{},{},{},{},{:?}\n",
+                escape_csv(&reading.device),
+                data.co2,
+                data.temperature,
+                data.humidity_percent(),
+                data.pressure,
+                data.battery,
+                data.status,
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl Exporter for FileExporter {
+    async fn export(&mut self, readings: &[TaggedReading]) -> Result<(), ExportError> {
+        for reading in readings {
+            let line = self.format(reading);
+
+            self.open().await?.write_all(line.as_bytes()).await?;
+            self.size += line.len() as u64;
+        }
+
+        if let Some(file) = &mut self.file {
+            file.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), ExportError> {
+        if let Some(file) = &mut self.file {
+            file.flush().await?;
+            file.sync_data().await?;
+        }
+
+        Ok(())
+    }
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+
+    PathBuf::from(name)
+}
+
+fn today() -> u64 {
+    day(SystemTime::now())
+}
+
+/// The number of (UTC) days between the Unix epoch and `time`
+fn day(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / 86_400
+}
+
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn reading() -> TaggedReading {
+        TaggedReading {
+            device: "office".to_string(),
+            timestamp: UNIX_EPOCH,
+            data: crate::tests::reading(),
+        }
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aranet-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[tokio::test]
+    async fn rotates_by_size_and_prunes_old_files() {
+        let dir = scratch_dir("size");
+        let path = dir.join("aranet.log");
+        let mut exporter = FileExporter::new(&path, FileFormat::JsonLines)
+            .with_rotation(Rotation::Size(1))
+            .with_retention(2);
+
+        for _ in 0..4 {
+            exporter.export(&[reading()]).await.unwrap();
+        }
+
+        assert!(path.exists());
+        assert!(rotated(&path, 1).exists());
+        assert!(rotated(&path, 2).exists());
+        assert!(!rotated(&path, 3).exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rotates_a_file_left_from_a_previous_day() {
+        let dir = scratch_dir("daily");
+        let path = dir.join("aranet.csv");
+        std::fs::write(&path, "stale\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(2 * 86_400))
+            .unwrap();
+
+        let mut exporter = FileExporter::new(&path, FileFormat::Csv).with_rotation(Rotation::Daily);
+        exporter.export(&[reading()]).await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(rotated(&path, 1)).unwrap(),
+            "stale\n"
+        );
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("device,timestamp"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn appends_to_a_file_from_today() {
+        let dir = scratch_dir("today");
+        let path = dir.join("aranet.log");
+        std::fs::write(&path, "earlier\n").unwrap();
+
+        let mut exporter =
+            FileExporter::new(&path, FileFormat::JsonLines).with_rotation(Rotation::Daily);
+        exporter.export(&[reading()]).await.unwrap();
+
+        assert!(!rotated(&path, 1).exists());
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("earlier\n"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writes_the_precise_humidity() {
+        let mut reading = reading();
+        reading.data.humidity_precise = Some(45.5);
+
+        let line = FileExporter::new("aranet.log", FileFormat::JsonLines).format(&reading);
+        assert!(line.contains("\"humidity\":45.5,"));
+
+        let line = FileExporter::new("aranet.csv", FileFormat::Csv).format(&reading);
+        assert_eq!(line, "office,0,600,21.5,45.5,1013,90,GREEN\n");
+    }
+}
@@ -0,0 +1,134 @@
+use crate::SensorData;
+
+/// The largest change between consecutive readings that isn't considered a spike
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpikeThresholds {
+    /// Maximum change in CO2 concentration, in ppm
+    pub co2: u16,
+    /// Maximum change in temperature, in Celsius
+    pub temperature: f32,
+    /// Maximum change in relative humidity, in percentage points
+    pub humidity: u8,
+    /// Maximum change in atmospheric pressure, in hPa
+    pub pressure: u16,
+}
+
+impl Default for SpikeThresholds {
+    fn default() -> Self {
+        Self {
+            co2: 500,
+            temperature: 5.0,
+            humidity: 20,
+            pressure: 20,
+        }
+    }
+}
+
+/// The outcome of passing a reading through a [`SpikeFilter`]
+#[derive(Debug, Clone)]
+pub enum Filtered {
+    /// The reading is consistent with the previous ones
+    Accepted(SensorData),
+    /// The reading jumped away from the previous ones and hasn't been confirmed yet
+    Spike(SensorData),
+}
+
+/// Detects single-sample spikes, like CO2 jumping by 2000 ppm for one reading and then back
+///
+/// A reading that jumps past the thresholds is reported as a spike, unless the following reading
+/// confirms the new level, in which case it is accepted as a genuine change.
+#[derive(Debug, Clone, Default)]
+pub struct SpikeFilter {
+    thresholds: SpikeThresholds,
+    last: Option<SensorData>,
+    suspect: Option<SensorData>,
+}
+
+impl SpikeFilter {
+    /// Create a filter with the given thresholds
+    pub fn new(thresholds: SpikeThresholds) -> Self {
+        Self {
+            thresholds,
+            last: None,
+            suspect: None,
+        }
+    }
+
+    /// Pass a reading through the filter
+    pub fn filter(&mut self, data: SensorData) -> Filtered {
+        let jumped = self
+            .last
+            .as_ref()
+            .is_some_and(|last| self.jumped(last, &data));
+        let confirmed = self
+            .suspect
+            .take()
+            .is_some_and(|suspect| !self.jumped(&suspect, &data));
+
+        if jumped && !confirmed {
+            self.suspect = Some(data.clone());
+            return Filtered::Spike(data);
+        }
+
+        self.last = Some(data.clone());
+        Filtered::Accepted(data)
+    }
+
+    /// Pass a reading through the filter, dropping it if it is a spike
+    pub fn accept(&mut self, data: SensorData) -> Option<SensorData> {
+        match self.filter(data) {
+            Filtered::Accepted(data) => Some(data),
+            Filtered::Spike(_) => None,
+        }
+    }
+
+    fn jumped(&self, previous: &SensorData, current: &SensorData) -> bool {
+        previous.co2.abs_diff(current.co2) > self.thresholds.co2
+            || (previous.temperature - current.temperature).abs() > self.thresholds.temperature
+            || previous.humidity.abs_diff(current.humidity) > self.thresholds.humidity
+            || previous.pressure.abs_diff(current.pressure) > self.thresholds.pressure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::reading;
+
+    fn co2(co2: u16) -> SensorData {
+        SensorData { co2, ..reading() }
+    }
+
+    #[test]
+    fn drops_single_sample_spikes() {
+        let mut filter = SpikeFilter::default();
+
+        assert!(filter.accept(co2(600)).is_some());
+        assert!(filter.accept(co2(2600)).is_none());
+        assert_eq!(filter.accept(co2(610)).map(|data| data.co2), Some(610));
+    }
+
+    #[test]
+    fn accepts_a_confirmed_change() {
+        let mut filter = SpikeFilter::default();
+
+        assert!(filter.accept(co2(600)).is_some());
+        assert!(filter.accept(co2(2600)).is_none());
+        assert_eq!(filter.accept(co2(2650)).map(|data| data.co2), Some(2650));
+        assert!(filter.accept(co2(2700)).is_some());
+    }
+
+    #[test]
+    fn accepts_changes_within_the_thresholds() {
+        let mut filter = SpikeFilter::default();
+
+        assert!(filter.accept(co2(600)).is_some());
+        assert!(filter.accept(co2(1100)).is_some());
+        assert!(filter
+            .accept(SensorData {
+                temperature: 25.0,
+                ..co2(1100)
+            })
+            .is_some());
+    }
+}
@@ -0,0 +1,102 @@
+use btleplug::api::{CharPropFlags, Peripheral};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::Aranet4;
+
+/// The GATT services and characteristics exposed by a device
+///
+/// The [`Display`](fmt::Display) implementation renders an indented dump suitable for attaching
+/// to bug reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GattLayout {
+    /// The services exposed by the device
+    pub services: Vec<GattService>,
+}
+
+/// A GATT service exposed by a device
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GattService {
+    /// The service's UUID
+    pub uuid: Uuid,
+    /// Whether this is a primary service
+    pub primary: bool,
+    /// The characteristics belonging to the service
+    pub characteristics: Vec<GattCharacteristic>,
+}
+
+/// A GATT characteristic exposed by a device
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GattCharacteristic {
+    /// The characteristic's UUID
+    pub uuid: Uuid,
+    /// Whether the characteristic can be read
+    pub read: bool,
+    /// Whether the characteristic can be written to, with or without a response
+    pub write: bool,
+    /// Whether the characteristic sends notifications or indications
+    pub notify: bool,
+}
+
+impl<P: Peripheral> Aranet4<P> {
+    /// Get the services and characteristics discovered on the device
+    pub fn gatt_layout(&self) -> GattLayout {
+        GattLayout {
+            services: self
+                .device
+                .services()
+                .into_iter()
+                .map(|service| GattService {
+                    uuid: service.uuid,
+                    primary: service.primary,
+                    characteristics: service
+                        .characteristics
+                        .into_iter()
+                        .map(|characteristic| GattCharacteristic {
+                            uuid: characteristic.uuid,
+                            read: characteristic.properties.contains(CharPropFlags::READ),
+                            write: characteristic.properties.intersects(
+                                CharPropFlags::WRITE | CharPropFlags::WRITE_WITHOUT_RESPONSE,
+                            ),
+                            notify: characteristic
+                                .properties
+                                .intersects(CharPropFlags::NOTIFY | CharPropFlags::INDICATE),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for GattLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for service in &self.services {
+            let kind = if service.primary {
+                "primary"
+            } else {
+                "secondary"
+            };
+            writeln!(f, "{} ({kind})", service.uuid)?;
+
+            for characteristic in &service.characteristics {
+                let properties = [
+                    (characteristic.read, "read"),
+                    (characteristic.write, "write"),
+                    (characteristic.notify, "notify"),
+                ]
+                .into_iter()
+                .filter_map(|(supported, name)| supported.then_some(name))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+                writeln!(f, "  {} [{properties}]", characteristic.uuid)?;
+            }
+        }
+
+        Ok(())
+    }
+}
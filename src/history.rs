@@ -0,0 +1,715 @@
+use btleplug::api::Peripheral;
+use byteorder::{LittleEndian, ReadBytesExt};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use std::{
+    io::{self, Cursor},
+    ops::{Bound, RangeBounds},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant, SystemTime},
+};
+use uuid::{uuid, Uuid};
+
+use crate::{Aranet4, DeviceError, TimeoutError, COMMAND_CHARACTERISTIC};
+
+const TOTAL_READINGS_CHARACTERISTIC: Uuid = uuid!("f0cd2001-95da-4f4b-9ac8-aa55d312af0c");
+const INTERVAL_CHARACTERISTIC: Uuid = uuid!("f0cd2002-95da-4f4b-9ac8-aa55d312af0c");
+pub(crate) const HISTORY_V1_CHARACTERISTIC: Uuid = uuid!("f0cd2003-95da-4f4b-9ac8-aa55d312af0c");
+const SINCE_LAST_UPDATE_CHARACTERISTIC: Uuid = uuid!("f0cd2004-95da-4f4b-9ac8-aa55d312af0c");
+pub(crate) const HISTORY_V2_CHARACTERISTIC: Uuid = uuid!("f0cd2005-95da-4f4b-9ac8-aa55d312af0c");
+
+const HISTORY_V1_COMMAND: u8 = 0x82;
+const HISTORY_V2_COMMAND: u8 = 0x61;
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(10);
+/// Largest plausible deviation of the device's clock, beyond which estimates are clamped
+const MAX_CLOCK_DRIFT: f64 = 0.01;
+/// Shortest window the clock rate is estimated over. The counter only has a resolution of one
+/// second, so shorter windows can't resolve drifts below the clamp above
+const MIN_DRIFT_WINDOW: Duration = Duration::from_secs(600);
+
+/// A measurement stored on the device
+#[derive(Debug, Clone)]
+pub struct HistoryRecord {
+    /// When the measurement was taken, corrected for the drift of the device's clock
+    pub timestamp: SystemTime,
+    /// When the measurement was taken, assuming the device's clock keeps perfect time
+    pub raw_timestamp: SystemTime,
+    /// CO2 concentration in ppm
+    pub co2: u16,
+    /// Temperature in Celsius
+    pub temperature: f32,
+    /// Percentage of relative humidity
+    pub humidity: u8,
+    /// Atmospheric pressure in hPa
+    pub pressure: u16,
+}
+
+/// The quantities the device keeps a history of
+///
+/// The IDs match the `Param` enum in Aranet4-Python's client.py, which also numbers radiation
+/// pulses (6) and the dose integral (9).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Parameter {
+    Temperature = 1,
+    Humidity = 2,
+    Pressure = 3,
+    Co2 = 4,
+    /// Relative humidity in tenths of a percent, as stored by newer devices
+    Humidity2 = 5,
+    /// Accumulated radiation dose in nSv
+    RadiationDose = 7,
+    /// Radiation dose rate in nSv/h
+    RadiationDoseRate = 8,
+    /// Radon concentration in Bq/m³
+    Radon = 10,
+}
+
+impl Parameter {
+    /// Read a single stored value of this parameter, which is one byte wide for humidity, four for
+    /// the radiation and radon values, and two for everything else
+    fn read_value(self, payload: &mut Cursor<Vec<u8>>) -> io::Result<u32> {
+        match self {
+            Self::Humidity => payload.read_u8().map(u32::from),
+            Self::Radon | Self::RadiationDose | Self::RadiationDoseRate => {
+                payload.read_u32::<LittleEndian>()
+            }
+            _ => payload.read_u16::<LittleEndian>().map(u32::from),
+        }
+    }
+}
+
+/// A record type that can be assembled from a device's stored history
+pub(crate) trait StoredRecord: Sized + Send {
+    /// The parameters making up each record, in the order their values are passed to `from_values`
+    const PARAMETERS: &'static [Parameter];
+
+    /// Build a record from the values of each parameter
+    fn from_values(timestamp: SystemTime, raw_timestamp: SystemTime, values: &[u32]) -> Self;
+}
+
+impl StoredRecord for HistoryRecord {
+    const PARAMETERS: &'static [Parameter] = &[
+        Parameter::Co2,
+        Parameter::Temperature,
+        Parameter::Humidity,
+        Parameter::Pressure,
+    ];
+
+    fn from_values(timestamp: SystemTime, raw_timestamp: SystemTime, values: &[u32]) -> Self {
+        Self {
+            timestamp,
+            raw_timestamp,
+            co2: values[0] as u16,
+            temperature: values[1] as f32 / 20.0,
+            humidity: values[2] as u8,
+            pressure: (values[3] / 10) as u16,
+        }
+    }
+}
+
+/// How far along a history download is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of records downloaded so far
+    pub fetched: usize,
+    /// Total number of records being downloaded
+    pub total: usize,
+}
+
+/// Information needed to place history records in time
+struct Metadata {
+    total: u16,
+    interval: Duration,
+    /// When the latest measurement was taken, corrected for clock drift
+    last_update: SystemTime,
+    /// When the latest measurement was taken, going by the device's clock
+    raw_last_update: SystemTime,
+    /// How many host seconds pass for each second on the device's clock
+    clock_rate: f64,
+}
+
+impl Metadata {
+    /// When the record at the given (1-based) index was taken, corrected for clock drift
+    fn timestamp(&self, index: u16) -> SystemTime {
+        self.last_update - self.corrected_interval() * u32::from(self.total - index)
+    }
+
+    /// When the record at the given (1-based) index was taken, going by the device's clock
+    fn raw_timestamp(&self, index: u16) -> SystemTime {
+        self.raw_last_update - self.interval * u32::from(self.total - index)
+    }
+
+    /// The time between measurements as measured by the host's clock
+    fn corrected_interval(&self) -> Duration {
+        self.interval.mul_f64(self.clock_rate)
+    }
+
+    /// How many intervals before the last update `time` is, rounded down and up, or `None` if
+    /// it's after the last update
+    fn intervals_before(&self, time: &SystemTime) -> Option<(u128, u128)> {
+        let elapsed = self.last_update.duration_since(*time).ok()?.as_nanos();
+        let interval = self.corrected_interval().as_nanos().max(1);
+
+        Some((elapsed / interval, elapsed.div_ceil(interval)))
+    }
+
+    /// The (1-based) indices of the first and last records taken within `range`. The first is
+    /// greater than the last when no records fall in it
+    fn indices(&self, range: &impl RangeBounds<SystemTime>) -> (u16, u16) {
+        let total = u128::from(self.total);
+        let index = |intervals: u128| (total - intervals.min(total)) as u16;
+
+        let first = match range.start_bound() {
+            Bound::Unbounded => 1,
+            Bound::Included(time) => match self.intervals_before(time) {
+                Some((floor, _)) => index(floor).max(1),
+                None => self.total + 1,
+            },
+            Bound::Excluded(time) => match self.intervals_before(time) {
+                Some((_, ceil)) if ceil > 0 => index(ceil - 1).max(1),
+                _ => self.total + 1,
+            },
+        };
+
+        let last = match range.end_bound() {
+            Bound::Unbounded => self.total,
+            Bound::Included(time) => self
+                .intervals_before(time)
+                .map_or(self.total, |(_, ceil)| index(ceil)),
+            Bound::Excluded(time) => self
+                .intervals_before(time)
+                .map_or(self.total, |(floor, _)| index(floor + 1)),
+        };
+
+        (first, last)
+    }
+}
+
+/// State of an ongoing history download
+struct Download {
+    metadata: Metadata,
+    first: u16,
+    next: u16,
+    last: u16,
+}
+
+impl<P: Peripheral> Aranet4<P> {
+    /// Download all the measurements stored on the device
+    pub async fn history(&self) -> Result<Vec<HistoryRecord>, DeviceError> {
+        self.history_stream().try_collect().await
+    }
+
+    /// Download the measurements stored on the device taken at or after `since`
+    pub async fn history_since(
+        &self,
+        since: SystemTime,
+    ) -> Result<Vec<HistoryRecord>, DeviceError> {
+        self.history_range(since..).await
+    }
+
+    /// Download the measurements stored on the device taken within `range`
+    ///
+    /// Only the matching records are transferred, based on the device's measurement interval and
+    /// the time since its last update.
+    pub async fn history_range(
+        &self,
+        range: impl RangeBounds<SystemTime> + Send,
+    ) -> Result<Vec<HistoryRecord>, DeviceError> {
+        self.history_records(move |metadata| metadata.indices(&range), |_| {})
+            .try_collect()
+            .await
+    }
+
+    /// Download the measurements stored on the device, yielding them as they arrive
+    ///
+    /// Records are requested in chunks (see [`HistoryOptions`](crate::HistoryOptions)), so the
+    /// first ones are available long before the transfer completes. If the connection drops, the
+    /// device is reconnected and the transfer resumes from the chunk that was interrupted.
+    /// Dropping the stream cancels the rest of the transfer.
+    pub fn history_stream(&self) -> impl Stream<Item = Result<HistoryRecord, DeviceError>> + '_ {
+        self.history_stream_with_progress(|_| {})
+    }
+
+    /// Like [`Aranet4::history_stream`], calling `progress` after each chunk is downloaded
+    pub fn history_stream_with_progress<'a>(
+        &'a self,
+        progress: impl FnMut(Progress) + Send + 'a,
+    ) -> impl Stream<Item = Result<HistoryRecord, DeviceError>> + 'a {
+        self.history_records(|metadata| (1, metadata.total), progress)
+    }
+
+    /// Download `count` of the measurements stored on the device, starting from the record at
+    /// index `start` (1-based, oldest first)
+    pub async fn history_page(
+        &self,
+        start: u16,
+        count: u16,
+    ) -> Result<Vec<HistoryRecord>, DeviceError> {
+        let start = start.max(1);
+        let end = start.saturating_add(count).saturating_sub(1);
+
+        self.history_records(move |metadata| (start, end.min(metadata.total)), |_| {})
+            .try_collect()
+            .await
+    }
+
+    /// Download all the records stored on the device, for devices other than the Aranet4
+    pub(crate) async fn stored_history<R: StoredRecord>(&self) -> Result<Vec<R>, DeviceError> {
+        self.history_records(|metadata| (1, metadata.total), |_| {})
+            .try_collect()
+            .await
+    }
+
+    /// Download the records between the indices returned by `select`, given the history's metadata
+    fn history_records<'a, R: StoredRecord + 'a>(
+        &'a self,
+        select: impl Fn(&Metadata) -> (u16, u16) + Send + 'a,
+        progress: impl FnMut(Progress) + Send + 'a,
+    ) -> impl Stream<Item = Result<R, DeviceError>> + 'a {
+        stream::try_unfold(
+            (None, select, progress),
+            move |(download, select, mut progress): (Option<Download>, _, _)| async move {
+                let download = match download {
+                    Some(download) => download,
+                    None => {
+                        let metadata = self.history_metadata().await?;
+                        let (first, last) = select(&metadata);
+
+                        Download {
+                            metadata,
+                            first,
+                            next: first,
+                            last,
+                        }
+                    }
+                };
+
+                if download.next > download.last {
+                    return Ok::<_, DeviceError>(None);
+                }
+
+                let end = download
+                    .next
+                    .saturating_add(self.history_options.chunk_size.max(1) - 1)
+                    .min(download.last);
+                let records = self
+                    .history_chunk_resuming(&download.metadata, download.next, end)
+                    .await?;
+
+                progress(Progress {
+                    fetched: usize::from(end - download.first + 1),
+                    total: usize::from(download.last - download.first + 1),
+                });
+
+                Ok(Some((
+                    stream::iter(records.into_iter().map(Ok)),
+                    (
+                        Some(Download {
+                            next: end + 1,
+                            ..download
+                        }),
+                        select,
+                        progress,
+                    ),
+                )))
+            },
+        )
+        .try_flatten()
+    }
+
+    async fn history_metadata(&self) -> Result<Metadata, DeviceError> {
+        let total = self.retrying(|| self.total_readings()).await?;
+        let interval = self.retrying(|| self.interval()).await?;
+        let clock_rate = match self.history_options.drift_window {
+            Some(_) => self.clock_rate().await?,
+            None => 1.0,
+        };
+        let since_last_update = self.retrying(|| self.since_last_update()).await?;
+        let now = SystemTime::now();
+
+        Ok(Metadata {
+            total,
+            interval,
+            last_update: now - since_last_update.mul_f64(clock_rate),
+            raw_last_update: now - since_last_update,
+            clock_rate,
+        })
+    }
+
+    /// Estimate how many host seconds pass for each second on the device's clock
+    ///
+    /// The device's "seconds since last update" counter is compared at either end of the drift
+    /// window from [`HistoryOptions`](crate::HistoryOptions) (at least ten minutes). The estimate
+    /// is made once and reused by every later call on the handle, including history downloads.
+    pub async fn clock_rate(&self) -> Result<f64, DeviceError> {
+        let window = self
+            .history_options
+            .drift_window
+            .unwrap_or(MIN_DRIFT_WINDOW)
+            .max(MIN_DRIFT_WINDOW);
+
+        self.clock_rate
+            .get_or_try_init(|| self.estimate_clock_rate(window))
+            .await
+            .copied()
+    }
+
+    async fn estimate_clock_rate(&self, window: Duration) -> Result<f64, DeviceError> {
+        let interval = self.retrying(|| self.interval()).await?;
+        let (start, start_seconds) = self.retrying(|| self.timed_counter()).await?;
+        tokio::time::sleep(window).await;
+        let (end, end_seconds) = self.retrying(|| self.timed_counter()).await?;
+
+        let host_elapsed = end.duration_since(start).as_secs_f64();
+
+        // The counter resets whenever a measurement is taken, so add back however many intervals
+        // brings the device's elapsed time closest to the host's
+        let interval = interval.as_secs_f64();
+        let counted = f64::from(end_seconds) - f64::from(start_seconds);
+        let device_elapsed = counted + ((host_elapsed - counted) / interval).round() * interval;
+
+        if device_elapsed <= 0.0 {
+            return Ok(1.0);
+        }
+
+        Ok((host_elapsed / device_elapsed).clamp(1.0 - MAX_CLOCK_DRIFT, 1.0 + MAX_CLOCK_DRIFT))
+    }
+
+    /// Read the device's "seconds since last update" counter, along with the host's time halfway
+    /// through the read
+    async fn timed_counter(&self) -> Result<(Instant, u16), DeviceError> {
+        let before = Instant::now();
+        let seconds = self.read_u16(SINCE_LAST_UPDATE_CHARACTERISTIC).await?;
+
+        Ok((before + before.elapsed() / 2, seconds))
+    }
+
+    /// Download the records `start..=end` (1-based), reconnecting and trying again if the
+    /// transfer is interrupted
+    async fn history_chunk_resuming<R: StoredRecord>(
+        &self,
+        metadata: &Metadata,
+        start: u16,
+        end: u16,
+    ) -> Result<Vec<R>, DeviceError> {
+        let mut attempts = 1;
+
+        loop {
+            match self.history_chunk(metadata, start, end).await {
+                Err(err)
+                    if attempts < self.history_options.attempts
+                        && self.retry_policy.should_retry(&err) =>
+                {
+                    attempts += 1;
+                    tokio::time::sleep(self.retry_policy.backoff).await;
+
+                    self.ensure_connected().await?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Download the records `start..=end` (1-based)
+    async fn history_chunk<R: StoredRecord>(
+        &self,
+        metadata: &Metadata,
+        start: u16,
+        end: u16,
+    ) -> Result<Vec<R>, DeviceError> {
+        let mut values = Vec::with_capacity(R::PARAMETERS.len());
+        for &parameter in R::PARAMETERS {
+            values.push(self.history_values(parameter, start, end).await?);
+        }
+
+        Ok((start..=end)
+            .enumerate()
+            .map(|(offset, index)| {
+                let record: Vec<u32> = values.iter().map(|values| values[offset]).collect();
+
+                R::from_values(
+                    metadata.timestamp(index),
+                    metadata.raw_timestamp(index),
+                    &record,
+                )
+            })
+            .collect())
+    }
+
+    /// Download the values of a parameter for the records `start..=end` (1-based), using the
+    /// newer protocol when the firmware supports it (v1.2 and later)
+    async fn history_values(
+        &self,
+        parameter: Parameter,
+        start: u16,
+        end: u16,
+    ) -> Result<Vec<u32>, DeviceError> {
+        if self.characteristic(HISTORY_V2_CHARACTERISTIC).is_ok() {
+            self.history_v2(parameter, start, end).await
+        } else if self.characteristic(HISTORY_V1_CHARACTERISTIC).is_ok() {
+            self.history_v1(parameter, start, end).await
+        } else {
+            Err(DeviceError::Unsupported("history"))
+        }
+    }
+
+    /// Download the values of a parameter for the records `start..=end` (1-based) using the
+    /// read-based protocol, where each read returns the next chunk of records
+    async fn history_v2(
+        &self,
+        parameter: Parameter,
+        start: u16,
+        end: u16,
+    ) -> Result<Vec<u32>, DeviceError> {
+        let count = usize::from(end - start + 1);
+        let mut values = Vec::with_capacity(count);
+
+        while values.len() < count {
+            let next = start + values.len() as u16;
+            let [next_lo, next_hi] = next.to_le_bytes();
+            self.write(
+                COMMAND_CHARACTERISTIC,
+                &[HISTORY_V2_COMMAND, parameter as u8, next_lo, next_hi],
+            )
+            .await?;
+
+            let mut payload = Cursor::new(self.read(HISTORY_V2_CHARACTERISTIC).await?);
+
+            let response_parameter = payload.read_u8()?;
+            let _interval = payload.read_u16::<LittleEndian>()?;
+            let _total = payload.read_u16::<LittleEndian>()?;
+            let _since_last_update = payload.read_u16::<LittleEndian>()?;
+            let index = payload.read_u16::<LittleEndian>()?;
+            let length = payload.read_u8()?;
+
+            if response_parameter != parameter as u8 || index != next || length == 0 {
+                break;
+            }
+
+            for _ in 0..length {
+                if values.len() == count {
+                    break;
+                }
+
+                values.push(parameter.read_value(&mut payload)?);
+            }
+        }
+
+        if values.len() < count {
+            return Err(DeviceError::IncompleteHistory {
+                expected: count,
+                received: values.len(),
+            });
+        }
+
+        Ok(values)
+    }
+
+    /// Download the values of a parameter for the records `start..=end` (1-based) using the
+    /// notification-based protocol
+    async fn history_v1(
+        &self,
+        parameter: Parameter,
+        start: u16,
+        end: u16,
+    ) -> Result<Vec<u32>, DeviceError> {
+        let characteristic = self.characteristic(HISTORY_V1_CHARACTERISTIC)?;
+
+        // Clean up after a download that was dropped midway, so its leftover notifications stop
+        if self.abandoned_history.swap(false, Ordering::Relaxed) {
+            let _ = self.device.unsubscribe(&characteristic).await;
+        }
+
+        let mut notifications = self.device.notifications().await?;
+        self.device.subscribe(&characteristic).await?;
+        let subscription = Subscription(&self.abandoned_history);
+
+        let count = usize::from(end - start + 1);
+        let values = async {
+            let [start_lo, start_hi] = start.to_le_bytes();
+            let [end_lo, end_hi] = end.to_le_bytes();
+            self.write(
+                COMMAND_CHARACTERISTIC,
+                &[
+                    HISTORY_V1_COMMAND,
+                    parameter as u8,
+                    0,
+                    0,
+                    start_lo,
+                    start_hi,
+                    end_lo,
+                    end_hi,
+                ],
+            )
+            .await?;
+
+            let mut values = vec![None; count];
+            let mut received = 0;
+
+            while received < count {
+                let notification = tokio::time::timeout(NOTIFICATION_TIMEOUT, notifications.next())
+                    .await
+                    .map_err(|_| TimeoutError {
+                        operation: "history notification",
+                        after: NOTIFICATION_TIMEOUT,
+                    })?
+                    .ok_or(btleplug::Error::NotConnected)?;
+
+                if notification.uuid != HISTORY_V1_CHARACTERISTIC {
+                    continue;
+                }
+                let size = notification.value.len();
+                self.stats.record_notification(size);
+
+                let mut payload = Cursor::new(notification.value);
+                if payload.read_u8()? != parameter as u8 {
+                    continue;
+                }
+
+                let index = payload.read_u16::<LittleEndian>()?;
+                let length = payload.read_u8()?;
+                if length == 0 {
+                    break;
+                }
+
+                for offset in 0..u16::from(length) {
+                    let value = parameter.read_value(&mut payload)?;
+
+                    let Some(slot) = index
+                        .checked_add(offset)
+                        .ok_or(DeviceError::UnexpectedPayload(size))?
+                        .checked_sub(start)
+                        .and_then(|i| values.get_mut(usize::from(i)))
+                    else {
+                        continue;
+                    };
+
+                    if slot.replace(value).is_none() {
+                        received += 1;
+                    }
+                }
+            }
+
+            if received < count {
+                return Err(DeviceError::IncompleteHistory {
+                    expected: count,
+                    received,
+                });
+            }
+
+            Ok(values.into_iter().flatten().collect())
+        }
+        .await;
+
+        self.device.unsubscribe(&characteristic).await?;
+        subscription.release();
+
+        values
+    }
+
+    /// How long ago the device took its latest measurement
+    pub(crate) async fn since_last_update(&self) -> Result<Duration, DeviceError> {
+        let seconds = self.read_u16(SINCE_LAST_UPDATE_CHARACTERISTIC).await?;
+
+        Ok(Duration::from_secs(u64::from(seconds)))
+    }
+
+    /// Get the number of measurements stored on the device
+    pub async fn total_readings(&self) -> Result<u16, DeviceError> {
+        self.read_u16(TOTAL_READINGS_CHARACTERISTIC).await
+    }
+
+    /// How often the device takes a measurement
+    pub async fn interval(&self) -> Result<Duration, DeviceError> {
+        let interval = self.read_u16(INTERVAL_CHARACTERISTIC).await?;
+
+        Ok(Duration::from_secs(u64::from(interval)))
+    }
+
+    async fn read_u16(&self, uuid: Uuid) -> Result<u16, DeviceError> {
+        let mut payload = Cursor::new(self.read(uuid).await?);
+
+        Ok(payload.read_u16::<LittleEndian>()?)
+    }
+}
+
+/// Flags the history download as abandoned if it is dropped before unsubscribing, so the next one
+/// unsubscribes on its behalf
+struct Subscription<'a>(&'a AtomicBool);
+
+impl Subscription<'_> {
+    fn release(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for Subscription<'_> {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn metadata() -> Metadata {
+        let last_update = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        Metadata {
+            total: 10,
+            interval: MINUTE,
+            last_update,
+            raw_last_update: last_update,
+            clock_rate: 1.0,
+        }
+    }
+
+    #[test]
+    fn timestamps_count_back_from_the_last_update() {
+        let metadata = metadata();
+
+        assert_eq!(metadata.timestamp(10), metadata.last_update);
+        assert_eq!(metadata.timestamp(7), metadata.last_update - MINUTE * 3);
+    }
+
+    #[test]
+    fn unbounded_range_covers_every_record() {
+        assert_eq!(metadata().indices(&..), (1, 10));
+    }
+
+    #[test]
+    fn start_bounds_pick_the_first_record_in_range() {
+        let metadata = metadata();
+        let at = |minutes: u32| metadata.last_update - MINUTE * minutes;
+
+        assert_eq!(metadata.indices(&(at(3)..)).0, 7);
+        assert_eq!(metadata.indices(&(at(3) - MINUTE / 2..)).0, 7);
+        assert_eq!(
+            metadata
+                .indices(&(Bound::Excluded(at(3)), Bound::Unbounded))
+                .0,
+            8
+        );
+        assert_eq!(metadata.indices(&(at(100)..)).0, 1);
+    }
+
+    #[test]
+    fn end_bounds_pick_the_last_record_in_range() {
+        let metadata = metadata();
+        let at = |minutes: u32| metadata.last_update - MINUTE * minutes;
+
+        assert_eq!(metadata.indices(&(..=at(3))).1, 7);
+        assert_eq!(metadata.indices(&(..=at(3) + MINUTE / 2)).1, 7);
+        assert_eq!(metadata.indices(&(..at(3))).1, 6);
+    }
+
+    #[test]
+    fn ranges_after_the_last_update_are_empty() {
+        let metadata = metadata();
+        let (first, last) = metadata.indices(&(metadata.last_update + MINUTE..));
+
+        assert!(first > last);
+    }
+}
@@ -0,0 +1,33 @@
+/// The kind of Aranet device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    /// Aranet4 CO2, temperature, humidity and pressure monitor
+    Aranet4,
+    /// Aranet2 temperature and humidity monitor
+    Aranet2,
+    /// Aranet Radon Plus monitor
+    Radon,
+    /// Aranet Radiation monitor
+    Radiation,
+    /// An unrecognized device
+    Unknown,
+}
+
+impl DeviceKind {
+    /// Detect the kind of device from its advertised name or model number
+    pub fn from_name(name: &str) -> Self {
+        let name = name.trim_end_matches('\0');
+
+        if name.starts_with("Aranet4") {
+            Self::Aranet4
+        } else if name.starts_with("Aranet2") {
+            Self::Aranet2
+        } else if name.starts_with("AranetRn+") || name.starts_with("Aranet Radon") {
+            Self::Radon
+        } else if name.starts_with("Aranet\u{2622}") || name.starts_with("Aranet Radiation") {
+            Self::Radiation
+        } else {
+            Self::Unknown
+        }
+    }
+}
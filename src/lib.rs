@@ -1,18 +1,140 @@
+use adapter::all_adapters;
 use btleplug::{
-    api::{Central as _, Characteristic, Manager as _, Peripheral as _, ScanFilter},
-    platform::{Adapter, Manager, Peripheral},
+    api::{self, Central as _, Characteristic, Peripheral as _, WriteType},
+    platform::{Adapter, Peripheral},
 };
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::{io::Cursor, time::Duration};
+use connection::ConnectionMonitor;
+use discovery::{known_devices, seen_peripherals, with_properties};
+use futures::{stream::FuturesUnordered, StreamExt};
+use manager::Slot;
+use stats::StatsRecorder;
+use std::{
+    cmp::Reverse,
+    future::Future,
+    io::Cursor,
+    sync::{atomic::AtomicBool, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::sync::OnceCell;
 use uuid::{uuid, Uuid};
 
+pub use adapter::{adapters, AdapterInfo, AdapterSelector};
+pub use aranet2::{Aranet2, Aranet2Data, Aranet2Record};
+pub use calibration::Calibration;
+pub use capabilities::Capabilities;
+pub use client::{AranetClient, MockClient};
+pub use connection::{ConnectionEvent, DisconnectReason};
+pub use corrections::{Correction, Corrections};
+pub use device::AranetDevice;
+pub use discovery::{discover, scan, watch_devices, DiscoveredDevice, DiscoveryEvent, ScanStream};
+pub use export::{ExportError, Exporter, TaggedReading};
+pub use file::{FileExporter, FileFormat, Rotation};
+pub use filter::{Filtered, SpikeFilter, SpikeThresholds};
+pub use gatt::{GattCharacteristic, GattLayout, GattService};
+pub use history::{HistoryRecord, Progress};
+pub use kind::DeviceKind;
+pub use manager::AranetManager;
+pub use measurement::Measurement;
+#[cfg(feature = "notify")]
+pub use notify::NotificationExporter;
+pub use occupancy::{Occupancy, OccupancyEstimator, RoomParameters};
+pub use options::{
+    ConnectOptions, HistoryOptions, NameFilter, ReconnectPolicy, RetryPolicy, ScanMode, Selection,
+    Target, Timeouts,
+};
+pub use proximity::{estimate_distance, Proximity, RssiTracker};
+pub use radiation::{Radiation, RadiationData, RadiationRecord};
+pub use radon::{Radon, RadonData, RadonRecord};
+pub use room::{RoomAggregator, RoomReading};
+pub use sensor::Sensor;
+pub use smoothing::{Ema, Kalman, ReadingSmoother, Smoother};
+pub use stats::Stats;
+pub use sync::HistoryCursor;
+pub use validation::{ImplausibleReading, Metric, ValidationRules};
+pub use version::{ParseVersionError, Version};
+pub use watch::StatusChange;
+
+mod adapter;
+mod aranet2;
+mod calibration;
+mod capabilities;
+mod client;
+mod connection;
+mod corrections;
+#[cfg(feature = "defmt")]
+mod defmt_impl;
+mod device;
+mod discovery;
+mod export;
+mod file;
+mod filter;
+mod gatt;
+mod history;
+mod kind;
+mod manager;
+mod measurement;
+#[cfg(feature = "notify")]
+mod notify;
+mod occupancy;
+mod options;
+mod proximity;
+mod radiation;
+mod radon;
+mod room;
+mod sensor;
+mod settings;
+mod smoothing;
+mod stats;
+mod sync;
+mod validation;
+mod version;
+mod watch;
+
 const ADVERTISED_SERVICE: Uuid = uuid!("0000fce0-0000-1000-8000-00805f9b34fb");
 const CURRENT_READINGS_CHARACTERISTIC: Uuid = uuid!("f0cd3001-95da-4f4b-9ac8-aa55d312af0c");
+const DETAILED_READINGS_CHARACTERISTIC: Uuid = uuid!("f0cd3003-95da-4f4b-9ac8-aa55d312af0c");
+/// Size of the current readings payload
+const READINGS_LENGTH: usize = 13;
+/// Size of the current readings payload on firmware that appends a 2-byte measurement counter
+const COUNTED_READINGS_LENGTH: usize = READINGS_LENGTH + 2;
+/// Size of the detailed readings payload, not counting any additional fields after it
+const DETAILED_READINGS_LENGTH: usize = 14;
+/// Size of the current readings payload on old firmware
+const LEGACY_READINGS_LENGTH: usize = 9;
+/// Current readings of the Aranet devices other than the Aranet4
+const SENSOR_READINGS_CHARACTERISTIC: Uuid = uuid!("f0cd1504-95da-4f4b-9ac8-aa55d312af0c");
+/// How long to keep scanning for other matching devices after the first one, when the selection
+/// strategy compares them
+const SELECTION_WINDOW: Duration = Duration::from_secs(2);
+const COMMAND_CHARACTERISTIC: Uuid = uuid!("f0cd1402-95da-4f4b-9ac8-aa55d312af0c");
+const MODEL_NUMBER_CHARACTERISTIC: Uuid = uuid!("00002a24-0000-1000-8000-00805f9b34fb");
+const SERIAL_NUMBER_CHARACTERISTIC: Uuid = uuid!("00002a25-0000-1000-8000-00805f9b34fb");
+const FIRMWARE_REVISION_CHARACTERISTIC: Uuid = uuid!("00002a26-0000-1000-8000-00805f9b34fb");
+const HARDWARE_REVISION_CHARACTERISTIC: Uuid = uuid!("00002a27-0000-1000-8000-00805f9b34fb");
+const SOFTWARE_REVISION_CHARACTERISTIC: Uuid = uuid!("00002a28-0000-1000-8000-00805f9b34fb");
+const MANUFACTURER_NAME_CHARACTERISTIC: Uuid = uuid!("00002a29-0000-1000-8000-00805f9b34fb");
 
 /// A connection to an Aranet4 device
-pub struct Aranet4 {
-    device: Peripheral,
-    current_readings: Characteristic,
+///
+/// Generic over the btleplug peripheral backing the connection, defaulting to the platform one.
+pub struct Aranet4<P: api::Peripheral = Peripheral> {
+    device: P,
+    /// Refreshed on reconnect, as some platforms invalidate the handles of a previous connection
+    current_readings: RwLock<Characteristic>,
+    corrections: Corrections,
+    history_options: HistoryOptions,
+    stats: StatsRecorder,
+    connection: ConnectionMonitor,
+    reconnect_policy: Option<ReconnectPolicy>,
+    timeouts: Timeouts,
+    retry_policy: RetryPolicy,
+    /// The [`AranetManager`] connection slot, released when the handle is dropped
+    slot: Option<Slot>,
+    /// Set when a history download was dropped before unsubscribing from its notifications
+    abandoned_history: AtomicBool,
+    /// Estimated once, as it takes at least ten minutes
+    clock_rate: OnceCell<f64>,
 }
 
 /// Errors that can occur when connecting to an Aranet4 device
@@ -24,61 +146,162 @@ pub enum ConnectionError {
 
     /// Could not find an Aranet4 device before timeout
     #[error("Failed to find an Aranet4 device before timeout")]
-    SearchTimeout,
+    SearchTimeout {
+        /// The Aranet devices that were seen during the scan, but didn't match
+        seen: Vec<DiscoveredDevice>,
+    },
 
     /// The specified characteristic was not found
     #[error("The characteristic {0} was not found")]
     CharacteristicNotFound(String),
 
+    /// Several devices matched, and the selection strategy requires a single one
+    #[error("Found {} matching Aranet devices, but expected only one", candidates.len())]
+    AmbiguousDevice {
+        /// The devices that matched
+        candidates: Vec<DiscoveredDevice>,
+    },
+
+    /// The device is not a supported Aranet model
+    #[error("The device is not a supported Aranet model")]
+    UnsupportedDevice,
+
+    /// Connecting to the device or discovering its services took too long
+    #[error(transparent)]
+    Timeout(#[from] TimeoutError),
+
+    /// More devices were requested at once than the manager's connection limit allows
+    #[error("Requested {requested} connections, but the connection limit is {limit}")]
+    ConnectionLimit {
+        /// The number of connections requested
+        requested: usize,
+        /// The manager's connection limit
+        limit: usize,
+    },
+
     /// Other Bluetooth errors
     #[error(transparent)]
     BTLE(#[from] btleplug::Error),
 }
 
+impl ConnectionError {
+    /// Whether the failure is temporary, and retrying the operation may succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::SearchTimeout { .. } | Self::Timeout(_) => true,
+            Self::BTLE(err) => is_transient(err),
+            Self::AdapterUnavaliable
+            | Self::AmbiguousDevice { .. }
+            | Self::CharacteristicNotFound(_)
+            | Self::ConnectionLimit { .. }
+            | Self::UnsupportedDevice => false,
+        }
+    }
+}
+
 /// Find an Aranet4 device and connect to it
 pub async fn connect() -> Result<Aranet4, ConnectionError> {
-    let manager = Manager::new().await.unwrap();
+    connect_with_options(ConnectOptions::default()).await
+}
 
-    let adapters = manager
-        .adapters()
+/// Find an Aranet4 device and connect to it, using the given options
+pub async fn connect_with_options(options: ConnectOptions) -> Result<Aranet4, ConnectionError> {
+    AranetManager::with_adapter(&options.adapter)
+        .await?
+        .connect_with_options(options)
         .await
-        .map_err(|_| ConnectionError::AdapterUnavaliable)?;
+}
 
-    let adapter = adapters
-        .first()
-        .ok_or(ConnectionError::AdapterUnavaliable)?;
+/// Find an Aranet4 device using an existing Bluetooth adapter and connect to it
+pub async fn connect_with_adapter(
+    adapter: &Adapter,
+    options: ConnectOptions,
+) -> Result<Aranet4, ConnectionError> {
+    let candidates = scan_for(adapter, &options, |deadline| {
+        find_candidates(adapter, &options, deadline)
+    })
+    .await?;
 
-    adapter
-        .start_scan(ScanFilter {
-            services: vec![ADVERTISED_SERVICE],
-        })
-        .await?;
+    select_device(candidates, &options.selection, &options.timeouts).await
+}
+
+/// Find an Aranet4 device by scanning with every available Bluetooth adapter at once, and connect
+/// to it through whichever one sees it first
+///
+/// This helps on gateways with several adapters, where the device may only be in range of some of
+/// them. The `adapter` option is ignored.
+pub async fn connect_with_all_adapters(
+    options: ConnectOptions,
+) -> Result<Aranet4, ConnectionError> {
+    let adapters = all_adapters()
+        .await?
+        .into_iter()
+        .map(|(_, adapter)| adapter)
+        .collect::<Vec<_>>();
+
+    let mut attempts = adapters
+        .iter()
+        .map(|adapter| connect_with_adapter(adapter, options.clone()))
+        .collect::<FuturesUnordered<_>>();
 
-    let device = tokio::select! {
-        device = find_device(adapter) => device?,
-        _ = tokio::time::sleep(Duration::from_secs(10)) => {
-            return Err(ConnectionError::SearchTimeout)
+    let mut result = Err(ConnectionError::AdapterUnavaliable);
+    while let Some(attempt) = attempts.next().await {
+        result = attempt;
+        if result.is_ok() {
+            break;
         }
-    };
+    }
+    drop(attempts);
 
-    device.connect().await?;
+    if options.stop_scan {
+        for adapter in &adapters {
+            let _ = adapter.stop_scan().await;
+        }
+    }
 
-    let chars = device.characteristics();
-    let current_readings = chars
-        .into_iter()
-        .find(|c| c.uuid == CURRENT_READINGS_CHARACTERISTIC)
-        .ok_or(ConnectionError::CharacteristicNotFound(
-            CURRENT_READINGS_CHARACTERISTIC.to_string(),
-        ))?;
-
-    Ok(Aranet4 {
-        device,
-        current_readings,
-    })
+    result
+}
+
+/// Connect to a specific device using an existing Bluetooth adapter
+///
+/// Scanning is skipped when the adapter already knows about the device, like after an earlier
+/// scan or when it's been paired with the system.
+pub async fn connect_to(
+    adapter: &Adapter,
+    target: Target,
+    options: ConnectOptions,
+) -> Result<Aranet4, ConnectionError> {
+    if let Some(device) = known_device(adapter, &target).await? {
+        return Aranet4::from_peripheral_with_timeouts(device, options.timeouts).await;
+    }
+
+    connect_many(adapter, &[target], options)
+        .await?
+        .pop()
+        .ok_or(ConnectionError::SearchTimeout { seen: Vec::new() })
+}
+
+/// Find all of the given devices in a single scan using an existing Bluetooth adapter, and connect to them
+///
+/// The connections are returned in the same order as the targets.
+pub async fn connect_many(
+    adapter: &Adapter,
+    targets: &[Target],
+    options: ConnectOptions,
+) -> Result<Vec<Aranet4>, ConnectionError> {
+    let devices = scan_for(adapter, &options, |_| find_devices(adapter, targets)).await?;
+
+    let mut connections = Vec::with_capacity(devices.len());
+    for device in devices {
+        connections
+            .push(Aranet4::from_peripheral_with_timeouts(device, options.timeouts.clone()).await?);
+    }
+
+    Ok(connections)
 }
 
 /// Information about the Aranet4 device
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Info {
     /// The device's model number
     pub model_number: String,
@@ -86,6 +309,8 @@ pub struct Info {
     pub serial_number: String,
     /// The device's firmware revision
     pub firmware_revision: String,
+    /// The device's firmware revision, parsed into a comparable version
+    pub firmware_version: Option<Version>,
     /// The device's hardware revision
     pub hardware_revision: String,
     /// The device's software revision
@@ -94,17 +319,29 @@ pub struct Info {
     pub manufacturer_name: String,
 }
 
+impl Info {
+    /// The kind of device, detected from its model number
+    pub fn kind(&self) -> DeviceKind {
+        DeviceKind::from_name(&self.model_number)
+    }
+}
+
 /// Measurements from the Aranet4 device
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SensorData {
     // CO2 concentration in ppm
     pub co2: u16,
     // CO2 concentration status
     pub status: Status,
+    // Whether the reading is valid, or the sensor is in a transitional state
+    pub sensor_state: SensorState,
     // Percentage of battery remaining
     pub battery: u8,
     // Percentage of relative humidity
     pub humidity: u8,
+    // Percentage of relative humidity in tenths of a percent, when read from the detailed
+    // readings characteristic
+    pub humidity_precise: Option<f32>,
     // Atmospheric pressure in hPa
     pub pressure: u16,
     // Temperature in Celsius
@@ -113,11 +350,32 @@ pub struct SensorData {
     pub interval: Duration,
     // Time since last update
     pub since_last_update: Duration,
+    // Number of measurements taken by the device, the same for every reading of a measurement.
+    // Only reported by firmware that appends it to the current readings payload
+    pub counter: Option<u16>,
 }
 
-/// CO2 concentration status, as displayed by the device
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+impl SensorData {
+    /// The relative humidity at the best resolution available
+    pub(crate) fn humidity_percent(&self) -> f32 {
+        self.humidity_precise
+            .unwrap_or_else(|| f32::from(self.humidity))
+    }
+}
+
+/// A reading taken with as little airtime as possible, see [`Aranet4::quick_read`]
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// The current measurements
+    pub measurements: SensorData,
+    /// Signal strength of the device in dBm, if reported by the adapter
+    pub rssi: Option<i16>,
+}
+
+/// CO2 concentration status, as displayed by the device, ordered by severity
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum Status {
+    NONE = 0,
     GREEN = 1,
     AMBER = 2,
     RED = 3,
@@ -129,7 +387,28 @@ impl From<u8> for Status {
             1 => Status::GREEN,
             2 => Status::AMBER,
             3 => Status::RED,
-            _ => panic!("invalid semaphore value"),
+            _ => Status::NONE,
+        }
+    }
+}
+
+/// State of the CO2 sensor when a reading was taken
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SensorState {
+    /// The sensor is working normally and the reading is valid
+    Ready,
+    /// The sensor hasn't taken a measurement since powering on
+    WarmingUp,
+    /// The device reported placeholder values instead of a measurement
+    Invalid,
+}
+
+impl SensorState {
+    fn from_status(status: u8) -> Self {
+        if Status::from(status) == Status::NONE {
+            Self::WarmingUp
+        } else {
+            Self::Ready
         }
     }
 }
@@ -143,6 +422,33 @@ pub enum DeviceError {
     #[error("The device broadcasted an invalid value.")]
     InvalidAttribute(#[from] std::string::FromUtf8Error),
 
+    #[error("The characteristic {0} was not found")]
+    CharacteristicNotFound(Uuid),
+
+    #[error("The device sent {received} of the {expected} requested history records.")]
+    IncompleteHistory { expected: usize, received: usize },
+
+    #[error("The device sent a {0}-byte payload, which doesn't match any known layout.")]
+    UnexpectedPayload(usize),
+
+    #[error("The device's firmware doesn't support {0}.")]
+    Unsupported(&'static str),
+
+    #[error("The device can't take measurements every {0:?}, only every 1, 2, 5 or 10 minutes.")]
+    UnsupportedInterval(Duration),
+
+    #[error("Only {synced} of the {stored} records stored on the device have been synced.")]
+    UnsyncedHistory { synced: u16, stored: u16 },
+
+    #[error("The measurement interval couldn't be changed back to {interval:?}.")]
+    IntervalNotRestored {
+        interval: Duration,
+        source: Box<DeviceError>,
+    },
+
+    #[error(transparent)]
+    Timeout(#[from] TimeoutError),
+
     #[error(transparent)]
     IO(#[from] std::io::Error),
 
@@ -150,12 +456,118 @@ pub enum DeviceError {
     BTLE(#[from] btleplug::Error),
 }
 
-impl Aranet4 {
+/// A Bluetooth operation that was abandoned after taking longer than its timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("The {operation} didn't complete within {after:?}.")]
+pub struct TimeoutError {
+    /// The operation that timed out, like "read" or "connection"
+    pub operation: &'static str,
+    /// How long the operation was given
+    pub after: Duration,
+}
+
+impl DeviceError {
+    /// Whether the failure is temporary, and retrying the operation may succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::BTLE(err) => is_transient(err),
+            Self::IncompleteHistory { .. } | Self::Timeout(_) => true,
+            Self::MissingAttribute(_)
+            | Self::InvalidAttribute(_)
+            | Self::CharacteristicNotFound(_)
+            | Self::UnexpectedPayload(_)
+            | Self::Unsupported(_)
+            | Self::UnsupportedInterval(_)
+            | Self::UnsyncedHistory { .. }
+            | Self::IntervalNotRestored { .. }
+            | Self::IO(_) => false,
+        }
+    }
+}
+
+impl<P: api::Peripheral> Aranet4<P> {
+    /// Wrap an already connected peripheral
+    ///
+    /// The higher resolution detailed readings characteristic is used when the firmware has it.
+    pub fn new(device: P) -> Result<Self, ConnectionError> {
+        let chars = device.characteristics();
+        let current_readings = chars
+            .iter()
+            .find(|c| c.uuid == DETAILED_READINGS_CHARACTERISTIC)
+            .or_else(|| {
+                chars
+                    .iter()
+                    .find(|c| c.uuid == CURRENT_READINGS_CHARACTERISTIC)
+            })
+            .cloned()
+            .ok_or(ConnectionError::CharacteristicNotFound(
+                CURRENT_READINGS_CHARACTERISTIC.to_string(),
+            ))?;
+
+        Ok(Self::with_readings(device, current_readings))
+    }
+
+    /// Connect to a peripheral found through btleplug directly, discovering its services
+    ///
+    /// This lets applications that run their own scans hand over the device they found.
+    pub async fn from_peripheral(device: P) -> Result<Self, ConnectionError> {
+        Self::from_peripheral_with_timeouts(device, Timeouts::default()).await
+    }
+
+    /// Connect to a peripheral found through btleplug directly, abandoning the connection and
+    /// every later operation on the handle once they exceed the given timeouts
+    pub async fn from_peripheral_with_timeouts(
+        device: P,
+        timeouts: Timeouts,
+    ) -> Result<Self, ConnectionError> {
+        connect_peripheral(&device, &timeouts).await?;
+
+        Ok(Self::new(device)?.with_timeouts(timeouts))
+    }
+
+    /// Wrap an already connected peripheral, taking readings from the given characteristic
+    pub(crate) fn with_readings(device: P, current_readings: Characteristic) -> Self {
+        Self {
+            device,
+            current_readings: RwLock::new(current_readings),
+            corrections: Corrections::default(),
+            history_options: HistoryOptions::default(),
+            stats: StatsRecorder::default(),
+            connection: ConnectionMonitor::default(),
+            reconnect_policy: None,
+            timeouts: Timeouts::default(),
+            retry_policy: RetryPolicy::default(),
+            slot: None,
+            abandoned_history: AtomicBool::new(false),
+            clock_rate: OnceCell::new(),
+        }
+    }
+
+    /// Hold a connection slot for as long as the handle lives
+    pub(crate) fn with_slot(mut self, slot: Slot) -> Self {
+        self.slot = Some(slot);
+        self
+    }
+
+    /// Wrap an already connected peripheral of another kind of Aranet device, taking readings
+    /// from the characteristic with the given UUID
+    pub(crate) fn for_readings(device: P, uuid: Uuid) -> Result<Self, ConnectionError> {
+        let current_readings = device
+            .characteristics()
+            .into_iter()
+            .find(|c| c.uuid == uuid)
+            .ok_or(ConnectionError::CharacteristicNotFound(uuid.to_string()))?;
+
+        Ok(Self::with_readings(device, current_readings))
+    }
+
     /// Get the device information
     pub async fn info(&self) -> Result<Info, DeviceError> {
-        if !self.device.is_connected().await? {
-            self.reconnect().await?;
-        }
+        self.retrying(|| self.read_info()).await
+    }
+
+    async fn read_info(&self) -> Result<Info, DeviceError> {
+        self.ensure_connected().await?;
 
         let mut model_number = None;
         let mut serial_number = None;
@@ -165,29 +577,29 @@ impl Aranet4 {
         let mut manufacturer_name = None;
 
         for characteristic in self.device.characteristics() {
-            match characteristic.uuid.to_string().as_str() {
-                "00002a24-0000-1000-8000-00805f9b34fb" => {
-                    let res = self.device.read(&characteristic).await?;
+            match characteristic.uuid {
+                MODEL_NUMBER_CHARACTERISTIC => {
+                    let res = self.gatt_read(&characteristic).await?;
                     model_number = Some(String::from_utf8(res)?.trim_end_matches('\0').to_string());
                 }
-                "00002a25-0000-1000-8000-00805f9b34fb" => {
-                    let res = self.device.read(&characteristic).await?;
-                    serial_number = Some(String::from_utf8(res)?.to_string());
+                SERIAL_NUMBER_CHARACTERISTIC => {
+                    let res = self.gatt_read(&characteristic).await?;
+                    serial_number = Some(String::from_utf8(res)?);
                 }
-                "00002a26-0000-1000-8000-00805f9b34fb" => {
-                    let res = self.device.read(&characteristic).await?;
-                    firmware_revision = Some(String::from_utf8(res)?.to_string());
+                FIRMWARE_REVISION_CHARACTERISTIC => {
+                    let res = self.gatt_read(&characteristic).await?;
+                    firmware_revision = Some(String::from_utf8(res)?);
                 }
-                "00002a27-0000-1000-8000-00805f9b34fb" => {
-                    let res = self.device.read(&characteristic).await?;
-                    hardware_revision = Some(String::from_utf8(res)?.to_string());
+                HARDWARE_REVISION_CHARACTERISTIC => {
+                    let res = self.gatt_read(&characteristic).await?;
+                    hardware_revision = Some(String::from_utf8(res)?);
                 }
-                "00002a28-0000-1000-8000-00805f9b34fb" => {
-                    let res = self.device.read(&characteristic).await?;
-                    software_revision = Some(String::from_utf8(res)?.to_string());
+                SOFTWARE_REVISION_CHARACTERISTIC => {
+                    let res = self.gatt_read(&characteristic).await?;
+                    software_revision = Some(String::from_utf8(res)?);
                 }
-                "00002a29-0000-1000-8000-00805f9b34fb" => {
-                    let res = self.device.read(&characteristic).await?;
+                MANUFACTURER_NAME_CHARACTERISTIC => {
+                    let res = self.gatt_read(&characteristic).await?;
                     manufacturer_name =
                         Some(String::from_utf8(res)?.trim_end_matches('\0').to_string());
                 }
@@ -217,6 +629,7 @@ impl Aranet4 {
         Ok(Info {
             model_number,
             serial_number,
+            firmware_version: firmware_revision.parse().ok(),
             firmware_revision,
             hardware_revision,
             software_revision,
@@ -224,65 +637,709 @@ impl Aranet4 {
         })
     }
 
-    /// Get the current measurements from the device
+    /// Get the kind of device, detected from its advertised name
+    pub async fn kind(&self) -> Result<DeviceKind, DeviceError> {
+        let name = self
+            .device
+            .properties()
+            .await?
+            .and_then(|properties| properties.local_name);
+
+        Ok(name.map_or(DeviceKind::Unknown, |name| DeviceKind::from_name(&name)))
+    }
+
+    /// Apply the given corrections to all measurements read through this handle
+    pub fn with_corrections(mut self, corrections: Corrections) -> Self {
+        self.corrections = corrections;
+        self
+    }
+
+    /// Use the given options when downloading the history stored on the device
+    pub fn with_history_options(mut self, options: HistoryOptions) -> Self {
+        self.history_options = options;
+        self
+    }
+
+    /// Reconnect automatically, following the given policy, when an operation finds the connection
+    /// has dropped
+    ///
+    /// Without a policy, a single reconnection attempt is made.
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = Some(policy);
+        self
+    }
+
+    /// Retry reading information, measurements and history following the given policy when they
+    /// fail with a temporary error
+    ///
+    /// History chunks are tried as many times as [`HistoryOptions::attempts`] allows instead.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Abandon Bluetooth operations that take longer than the given timeouts
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Get the current measurements from the device, with any configured corrections applied
     pub async fn measurements(&self) -> Result<SensorData, DeviceError> {
-        if !self.device.is_connected().await? {
-            self.reconnect().await?;
+        Ok(self.corrections.apply(self.raw_measurements().await?))
+    }
+
+    /// Get the current measurements from the device, exactly as reported
+    pub async fn raw_measurements(&self) -> Result<SensorData, DeviceError> {
+        self.retrying(|| self.read_raw_measurements()).await
+    }
+
+    async fn read_raw_measurements(&self) -> Result<SensorData, DeviceError> {
+        self.ensure_connected().await?;
+
+        let current_readings = self.current_readings();
+        let data = self.gatt_read(&current_readings).await?;
+        let detailed = current_readings.uuid == DETAILED_READINGS_CHARACTERISTIC;
+
+        if !detailed && data.len() == LEGACY_READINGS_LENGTH {
+            return self.legacy_measurements(data).await;
         }
 
-        let mut payload = Cursor::new(self.device.read(&self.current_readings).await?);
+        let expected = if detailed {
+            DETAILED_READINGS_LENGTH
+        } else {
+            READINGS_LENGTH
+        };
+        if data.len() < expected {
+            return Err(DeviceError::UnexpectedPayload(data.len()));
+        }
+
+        // Only the current readings payload with exactly the counter appended is known to carry
+        // one. The detailed payload can have other fields after the ones parsed here
+        let counted = !detailed && data.len() == COUNTED_READINGS_LENGTH;
+        let mut payload = Cursor::new(data);
 
         let co2 = payload.read_u16::<LittleEndian>()?;
-        let temperature = payload.read_u16::<LittleEndian>()? as f32 / 20.0;
-        let pressure = payload.read_u16::<LittleEndian>()? / 10;
-        let humidity = payload.read_u8()?;
+        let raw_temperature = payload.read_u16::<LittleEndian>()?;
+        let raw_pressure = payload.read_u16::<LittleEndian>()?;
+        let humidity_precise = if detailed {
+            Some(f32::from(payload.read_u16::<LittleEndian>()?) / 10.0)
+        } else {
+            None
+        };
+        let humidity = match humidity_precise {
+            Some(humidity) => humidity.round() as u8,
+            None => payload.read_u8()?,
+        };
         let battery = payload.read_u8()?;
         let status = payload.read_u8()?;
         let update_interval = payload.read_u16::<LittleEndian>()?;
         let since_last_update = payload.read_u16::<LittleEndian>()?;
+        let counter = if counted {
+            Some(payload.read_u16::<LittleEndian>()?)
+        } else {
+            None
+        };
+
+        let sensor_state = if has_sentinel(
+            co2,
+            raw_temperature,
+            raw_pressure,
+            humidity_precise.unwrap_or(f32::from(humidity)),
+        ) {
+            SensorState::Invalid
+        } else {
+            SensorState::from_status(status)
+        };
 
         Ok(SensorData {
             co2,
             battery,
             humidity,
-            pressure,
-            temperature,
+            humidity_precise,
+            pressure: raw_pressure / 10,
+            temperature: f32::from(raw_temperature) / 20.0,
             status: Status::from(status),
+            sensor_state,
             interval: Duration::from_secs(update_interval as u64),
             since_last_update: Duration::from_secs(since_last_update as u64),
+            counter,
         })
     }
 
-    /// Reconnect to the device
+    /// Parse the shorter payload sent by old firmware, which leaves out the interval and time
+    /// since the last update
+    async fn legacy_measurements(&self, data: Vec<u8>) -> Result<SensorData, DeviceError> {
+        Ok(parse_legacy_readings(
+            data,
+            self.interval().await?,
+            self.since_last_update().await?,
+        )?)
+    }
+
+    /// Get the signal strength of the device in dBm, if reported by the adapter
+    pub async fn rssi(&self) -> Result<Option<i16>, DeviceError> {
+        Ok(self.device.properties().await?.and_then(|p| p.rssi))
+    }
+
+    /// Get the transmission power advertised by the device in dBm, if any
+    pub async fn tx_power(&self) -> Result<Option<i16>, DeviceError> {
+        Ok(self
+            .device
+            .properties()
+            .await?
+            .and_then(|p| p.tx_power_level))
+    }
+
+    /// Estimate the distance to the device in meters, if it advertises its transmission power
+    pub async fn distance(&self) -> Result<Option<f32>, DeviceError> {
+        let Some(properties) = self.device.properties().await? else {
+            return Ok(None);
+        };
+
+        Ok(properties
+            .rssi
+            .zip(properties.tx_power_level)
+            .map(|(rssi, tx_power)| estimate_distance(f32::from(rssi), tx_power)))
+    }
+
+    /// Connect, read only the current measurements and signal strength, and disconnect again
+    ///
+    /// Useful for battery-powered gateways polling many devices on a schedule.
+    pub async fn quick_read(&self) -> Result<Snapshot, DeviceError> {
+        let snapshot = async {
+            let measurements = self.measurements().await?;
+            let rssi = self.rssi().await?;
+
+            Ok(Snapshot { measurements, rssi })
+        }
+        .await;
+
+        self.disconnect().await?;
+
+        snapshot
+    }
+
+    /// Read the raw value of any characteristic exposed by the device
+    ///
+    /// Useful for experimenting with characteristics this crate doesn't know about yet.
+    pub async fn read_characteristic(&self, uuid: Uuid) -> Result<Vec<u8>, DeviceError> {
+        self.read(uuid).await
+    }
+
+    /// Write a raw value to any characteristic exposed by the device
+    ///
+    /// Nothing about the value is validated, so writing to the wrong characteristic may leave the
+    /// device misconfigured.
+    pub async fn write_characteristic(&self, uuid: Uuid, data: &[u8]) -> Result<(), DeviceError> {
+        self.write(uuid, data).await
+    }
+
+    fn characteristic(&self, uuid: Uuid) -> Result<Characteristic, DeviceError> {
+        self.device
+            .characteristics()
+            .into_iter()
+            .find(|c| c.uuid == uuid)
+            .ok_or(DeviceError::CharacteristicNotFound(uuid))
+    }
+
+    async fn read(&self, uuid: Uuid) -> Result<Vec<u8>, DeviceError> {
+        self.ensure_connected().await?;
+
+        self.gatt_read(&self.characteristic(uuid)?).await
+    }
+
+    async fn write(&self, uuid: Uuid, data: &[u8]) -> Result<(), DeviceError> {
+        self.ensure_connected().await?;
+
+        let characteristic = self.characteristic(uuid)?;
+        self.gatt_write(&characteristic, data).await?;
+
+        Ok(())
+    }
+
+    async fn gatt_read(&self, characteristic: &Characteristic) -> Result<Vec<u8>, DeviceError> {
+        let start = Instant::now();
+        let result =
+            with_timeout("read", self.timeouts.read, self.device.read(characteristic)).await;
+        self.stats.record_read(start.elapsed(), &result);
+
+        result
+    }
+
+    async fn gatt_write(
+        &self,
+        characteristic: &Characteristic,
+        data: &[u8],
+    ) -> Result<(), DeviceError> {
+        let start = Instant::now();
+        let result = with_timeout(
+            "write",
+            self.timeouts.write,
+            self.device
+                .write(characteristic, data, WriteType::WithResponse),
+        )
+        .await;
+        self.stats
+            .record_write(start.elapsed(), data.len(), &result);
+
+        result
+    }
+
+    /// The underlying btleplug peripheral, for calling APIs this crate doesn't wrap
+    pub fn peripheral(&self) -> &P {
+        &self.device
+    }
+
+    /// Unwrap the underlying btleplug peripheral
+    pub fn into_inner(self) -> P {
+        self.device
+    }
+
+    /// Get counters for the GATT operations performed on the device through this handle
+    pub fn stats(&self) -> Stats {
+        self.stats.snapshot()
+    }
+
+    /// Reconnect to the device, discovering its services again
     pub async fn reconnect(&self) -> Result<(), DeviceError> {
-        self.device.connect().await?;
+        self.connection.reconnecting();
+        with_timeout::<_, DeviceError>("connection", self.timeouts.connect, self.device.connect())
+            .await?;
+        with_timeout::<_, DeviceError>(
+            "service discovery",
+            self.timeouts.service_discovery,
+            self.device.discover_services(),
+        )
+        .await?;
+
+        let refreshed = self.characteristic(self.current_readings().uuid)?;
+        *self
+            .current_readings
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = refreshed;
 
         Ok(())
     }
 
+    fn current_readings(&self) -> Characteristic {
+        self.current_readings
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    async fn retrying<T, F: Future<Output = Result<T, DeviceError>>>(
+        &self,
+        operation: impl Fn() -> F,
+    ) -> Result<T, DeviceError> {
+        let mut attempts = 1;
+
+        loop {
+            match operation().await {
+                Err(err)
+                    if attempts < self.retry_policy.attempts
+                        && self.retry_policy.should_retry(&err) =>
+                {
+                    attempts += 1;
+                    tokio::time::sleep(self.retry_policy.backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn ensure_connected(&self) -> Result<(), DeviceError> {
+        if self.device.is_connected().await? {
+            return Ok(());
+        }
+
+        let Some(policy) = &self.reconnect_policy else {
+            return self.reconnect().await;
+        };
+
+        let mut retry = 0;
+        loop {
+            match self.reconnect().await {
+                Err(err) if err.is_transient() && retry < policy.max_retries => {
+                    tokio::time::sleep(policy.backoff(retry)).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Disconnect from the device
     pub async fn disconnect(&self) -> Result<(), DeviceError> {
+        self.connection.disconnecting();
         self.device.disconnect().await?;
 
         Ok(())
     }
 }
 
-async fn find_device(adapter: &Adapter) -> Result<Peripheral, btleplug::Error> {
+/// Scan for Aranet devices until `search` completes or the timeout elapses
+///
+/// `search` is given the deadline, and wins over the timeout if both are ready at once. The scan
+/// is always stopped when the search fails, and after it succeeds unless the options ask to keep
+/// scanning.
+async fn scan_for<T, F: Future<Output = Result<T, btleplug::Error>>>(
+    adapter: &Adapter,
+    options: &ConnectOptions,
+    search: impl FnOnce(Option<tokio::time::Instant>) -> F,
+) -> Result<T, ConnectionError> {
+    adapter.start_scan(options.scan_mode.filter()).await?;
+
+    let deadline = options
+        .timeout
+        .map(|timeout| tokio::time::Instant::now() + timeout);
+    let timeout = async {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    let result = tokio::select! {
+        biased;
+        found = search(deadline) => found.map_err(ConnectionError::from),
+        _ = timeout => Err(ConnectionError::SearchTimeout {
+            seen: known_devices(adapter).await.unwrap_or_default(),
+        }),
+    };
+
+    match result {
+        Ok(found) => {
+            if options.stop_scan {
+                adapter.stop_scan().await?;
+            }
+
+            Ok(found)
+        }
+        Err(err) => {
+            let _ = adapter.stop_scan().await;
+
+            Err(err)
+        }
+    }
+}
+
+/// Find the devices matching the options. Unless the selection strategy takes the first one,
+/// scanning carries on for [`SELECTION_WINDOW`] after the first match to find the others in range,
+/// or until the deadline if that comes first
+async fn find_candidates(
+    adapter: &Adapter,
+    options: &ConnectOptions,
+    deadline: Option<tokio::time::Instant>,
+) -> Result<Vec<DiscoveredDevice>, btleplug::Error> {
+    let mut peripherals = Box::pin(seen_peripherals(adapter.clone()).await?);
+    let mut candidates: Vec<DiscoveredDevice> = Vec::new();
+    let mut window = None;
+
     loop {
-        let peripherals = adapter.peripherals().await.unwrap();
+        let peripheral = match &mut window {
+            None => peripherals.next().await,
+            Some(window) => tokio::select! {
+                peripheral = peripherals.next() => peripheral,
+                _ = window => break,
+            },
+        };
+        let Some((peripheral, properties)) = peripheral else {
+            break;
+        };
+
+        let advertises_service = properties.services.contains(&ADVERTISED_SERVICE);
+        if !options.matches(
+            properties.address,
+            properties.local_name.as_deref(),
+            advertises_service,
+        ) {
+            continue;
+        }
 
-        for peripheral in peripherals.into_iter() {
-            let properties = peripheral.properties().await.unwrap().unwrap();
-            let Some(name) = properties.local_name else {
-                continue;
-            };
+        let candidate = DiscoveredDevice::new(peripheral, properties);
+        match candidates
+            .iter_mut()
+            .find(|existing| existing.peripheral.id() == candidate.peripheral.id())
+        {
+            Some(existing) => *existing = candidate,
+            None => candidates.push(candidate),
+        }
+
+        if options.selection == Selection::First {
+            break;
+        }
+        window.get_or_insert_with(|| {
+            let end = tokio::time::Instant::now() + SELECTION_WINDOW;
+            Box::pin(tokio::time::sleep_until(
+                deadline.map_or(end, |deadline| deadline.min(end)),
+            ))
+        });
+    }
 
-            if name.starts_with("Aranet4") {
-                return Ok(peripheral);
+    if candidates.is_empty() {
+        return Err(btleplug::Error::DeviceNotFound);
+    }
+
+    Ok(candidates)
+}
+
+/// Connect to the candidate picked by the selection strategy
+async fn select_device(
+    mut candidates: Vec<DiscoveredDevice>,
+    selection: &Selection,
+    timeouts: &Timeouts,
+) -> Result<Aranet4, ConnectionError> {
+    match selection {
+        Selection::First => {}
+        Selection::StrongestSignal => candidates.sort_by_key(|candidate| Reverse(candidate.rssi)),
+        Selection::Alphabetical => candidates.sort_by(|a, b| a.name.cmp(&b.name)),
+        Selection::Unique if candidates.len() > 1 => {
+            return Err(ConnectionError::AmbiguousDevice { candidates })
+        }
+        Selection::Unique => {}
+        Selection::SerialNumber(serial_number) => {
+            // Candidates are probed one at a time, each disconnected before the next is
+            // connected, so the search never holds more than one connection
+            for candidate in &candidates {
+                if let Ok(device) = Aranet4::from_peripheral_with_timeouts(
+                    candidate.peripheral.clone(),
+                    timeouts.clone(),
+                )
+                .await
+                {
+                    if matches!(device.info().await, Ok(info) if info.serial_number == *serial_number)
+                    {
+                        return Ok(device);
+                    }
+                }
+
+                if candidate.peripheral.is_connected().await? {
+                    candidate.peripheral.disconnect().await?;
+                }
             }
+
+            return Err(ConnectionError::SearchTimeout { seen: candidates });
+        }
+    }
+
+    candidates
+        .remove(0)
+        .connect_with_timeouts(timeouts.clone())
+        .await
+}
+
+async fn known_device(
+    adapter: &Adapter,
+    target: &Target,
+) -> Result<Option<Peripheral>, btleplug::Error> {
+    if let Target::Id(id) = target {
+        if let Ok(peripheral) = adapter.peripheral(id).await {
+            return Ok(Some(peripheral));
+        }
+    }
+
+    let mut lookups = adapter
+        .peripherals()
+        .await?
+        .into_iter()
+        .map(with_properties)
+        .collect::<FuturesUnordered<_>>();
+
+    while let Some(found) = lookups.next().await {
+        let Some((peripheral, properties)) = found else {
+            continue;
+        };
+
+        if target.matches(
+            &peripheral.id(),
+            properties.address,
+            properties.local_name.as_deref(),
+        ) {
+            return Ok(Some(peripheral));
+        }
+    }
+
+    Ok(None)
+}
+
+async fn find_devices(
+    adapter: &Adapter,
+    targets: &[Target],
+) -> Result<Vec<Peripheral>, btleplug::Error> {
+    if targets.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut found = vec![None; targets.len()];
+    let mut peripherals = Box::pin(seen_peripherals(adapter.clone()).await?);
+
+    while let Some((peripheral, properties)) = peripherals.next().await {
+        for (target, slot) in targets.iter().zip(found.iter_mut()) {
+            if slot.is_none()
+                && target.matches(
+                    &peripheral.id(),
+                    properties.address,
+                    properties.local_name.as_deref(),
+                )
+            {
+                *slot = Some(peripheral.clone());
+            }
+        }
+
+        if found.iter().all(Option::is_some) {
+            return Ok(found.into_iter().flatten().collect());
         }
+    }
+
+    Err(btleplug::Error::DeviceNotFound)
+}
+
+/// Connect to the peripheral unless it already is, and discover its services
+pub(crate) async fn connect_peripheral<P: api::Peripheral>(
+    device: &P,
+    timeouts: &Timeouts,
+) -> Result<(), ConnectionError> {
+    if !device.is_connected().await? {
+        with_timeout::<_, ConnectionError>("connection", timeouts.connect, device.connect())
+            .await?;
+    }
+
+    with_timeout(
+        "service discovery",
+        timeouts.service_discovery,
+        device.discover_services(),
+    )
+    .await
+}
+
+/// Fail with a [`TimeoutError`] if the operation doesn't complete within `after`
+pub(crate) async fn with_timeout<T, E: From<TimeoutError> + From<btleplug::Error>>(
+    operation: &'static str,
+    after: Duration,
+    future: impl Future<Output = Result<T, btleplug::Error>>,
+) -> Result<T, E> {
+    tokio::time::timeout(after, future)
+        .await
+        .map_err(|_| TimeoutError { operation, after })?
+        .map_err(E::from)
+}
+
+/// Parse the 9-byte readings payload sent by old firmware, which has the same fields as the
+/// current layout up to and including the status byte
+fn parse_legacy_readings(
+    data: Vec<u8>,
+    interval: Duration,
+    since_last_update: Duration,
+) -> std::io::Result<SensorData> {
+    let mut payload = Cursor::new(data);
+
+    let co2 = payload.read_u16::<LittleEndian>()?;
+    let raw_temperature = payload.read_u16::<LittleEndian>()?;
+    let raw_pressure = payload.read_u16::<LittleEndian>()?;
+    let humidity = payload.read_u8()?;
+    let battery = payload.read_u8()?;
+    let status = payload.read_u8()?;
+
+    let sensor_state = if has_sentinel(co2, raw_temperature, raw_pressure, f32::from(humidity)) {
+        SensorState::Invalid
+    } else {
+        SensorState::from_status(status)
+    };
+
+    Ok(SensorData {
+        co2,
+        battery,
+        humidity,
+        humidity_precise: None,
+        pressure: raw_pressure / 10,
+        temperature: f32::from(raw_temperature) / 20.0,
+        status: Status::from(status),
+        sensor_state,
+        interval,
+        since_last_update,
+        counter: None,
+    })
+}
+
+/// Whether any of the raw values is a placeholder the device reports when it has no measurement
+fn has_sentinel(co2: u16, temperature: u16, pressure: u16, humidity: f32) -> bool {
+    co2 & 0x8000 != 0 || temperature == u16::MAX || pressure == u16::MAX || humidity > 100.0
+}
+
+fn is_transient(err: &btleplug::Error) -> bool {
+    matches!(
+        err,
+        btleplug::Error::TimedOut(_)
+            | btleplug::Error::NotConnected
+            | btleplug::Error::DeviceNotFound
+            | btleplug::Error::UnexpectedCallback
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A plausible reading, for tests to tweak
+    pub(crate) fn reading() -> SensorData {
+        SensorData {
+            co2: 600,
+            status: Status::GREEN,
+            sensor_state: SensorState::Ready,
+            battery: 90,
+            humidity: 45,
+            humidity_precise: None,
+            pressure: 1013,
+            temperature: 21.5,
+            interval: Duration::from_secs(300),
+            since_last_update: Duration::ZERO,
+            counter: None,
+        }
+    }
+
+    #[test]
+    fn parses_legacy_readings() {
+        // 600 ppm, 21.5 °C, 1013.2 hPa, 45 %, 87 % battery, green
+        let payload = vec![0x58, 0x02, 0xae, 0x01, 0x94, 0x27, 0x2d, 0x57, 0x01];
+        let data =
+            parse_legacy_readings(payload, Duration::from_secs(300), Duration::from_secs(42))
+                .unwrap();
+
+        assert_eq!(data.co2, 600);
+        assert_eq!(data.temperature, 21.5);
+        assert_eq!(data.pressure, 1013);
+        assert_eq!(data.humidity, 45);
+        assert_eq!(data.battery, 87);
+        assert_eq!(data.status, Status::GREEN);
+        assert_eq!(data.sensor_state, SensorState::Ready);
+        assert_eq!(data.interval, Duration::from_secs(300));
+        assert_eq!(data.since_last_update, Duration::from_secs(42));
+    }
+
+    #[test]
+    fn reads_the_legacy_status_byte() {
+        let payload = vec![0x58, 0x02, 0xae, 0x01, 0x94, 0x27, 0x2d, 0x57, 0x02];
+        let data = parse_legacy_readings(payload, Duration::ZERO, Duration::ZERO).unwrap();
+
+        assert_eq!(data.status, Status::AMBER);
+        assert_eq!(data.sensor_state, SensorState::Ready);
+    }
+
+    #[test]
+    fn detects_placeholder_values() {
+        assert!(!has_sentinel(600, 430, 10132, 45.0));
+        assert!(has_sentinel(0x8000 | 600, 430, 10132, 45.0));
+        assert!(has_sentinel(600, u16::MAX, 10132, 45.0));
+        assert!(has_sentinel(600, 430, u16::MAX, 45.0));
+        assert!(has_sentinel(600, 430, 10132, 255.0));
+    }
 
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    #[test]
+    fn sensor_state_from_status() {
+        assert_eq!(SensorState::from_status(1), SensorState::Ready);
+        assert_eq!(SensorState::from_status(3), SensorState::Ready);
+        assert_eq!(SensorState::from_status(0), SensorState::WarmingUp);
     }
 }
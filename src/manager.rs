@@ -0,0 +1,181 @@
+use btleplug::platform::Adapter;
+use futures::Stream;
+use std::{num::NonZeroUsize, sync::Arc, time::Duration};
+use tokio::sync::Semaphore;
+
+use crate::{
+    adapter::select_adapter, connect_many, connect_to, connect_with_adapter, discover, scan,
+    watch_devices, AdapterSelector, Aranet4, ConnectOptions, ConnectionError, DiscoveredDevice,
+    DiscoveryEvent, ScanMode, ScanStream, Target,
+};
+
+/// Owns the Bluetooth adapter, so it can be shared between multiple device connections
+pub struct AranetManager {
+    adapter: Adapter,
+    connections: Option<(Arc<Semaphore>, usize)>,
+    scan_mode: ScanMode,
+}
+
+impl AranetManager {
+    /// Initialize the Bluetooth stack using the first available adapter
+    pub async fn new() -> Result<Self, ConnectionError> {
+        Self::with_adapter(&AdapterSelector::default()).await
+    }
+
+    /// Initialize the Bluetooth stack using the adapter picked by `selector`
+    ///
+    /// Use [`adapters`](crate::adapters) to list the ones available.
+    pub async fn with_adapter(selector: &AdapterSelector) -> Result<Self, ConnectionError> {
+        let adapter = select_adapter(selector).await?;
+
+        Ok(Self {
+            adapter,
+            connections: None,
+            scan_mode: ScanMode::default(),
+        })
+    }
+
+    /// Limit how many devices can be connected at once, queueing any other connection attempts
+    ///
+    /// Each handle holds its slot until it is dropped, so reads and reconnects through it count
+    /// against the limit too. Most adapters can only sustain a handful of simultaneous
+    /// connections.
+    pub fn with_connection_limit(mut self, limit: NonZeroUsize) -> Self {
+        self.connections = Some((Arc::new(Semaphore::new(limit.get())), limit.get()));
+        self
+    }
+
+    /// Change whether the adapter or this crate picks out the Aranet devices when scanning with
+    /// [`scan`](Self::scan), [`discover`](Self::discover) or [`watch_devices`](Self::watch_devices)
+    pub fn with_scan_mode(mut self, scan_mode: ScanMode) -> Self {
+        self.scan_mode = scan_mode;
+        self
+    }
+
+    /// The Bluetooth adapter used for scanning and connecting
+    pub fn adapter(&self) -> &Adapter {
+        &self.adapter
+    }
+
+    /// Find an Aranet4 device and connect to it
+    pub async fn connect(&self) -> Result<Aranet4, ConnectionError> {
+        self.connect_with_options(ConnectOptions::default()).await
+    }
+
+    /// Find an Aranet4 device and connect to it, using the given options
+    pub async fn connect_with_options(
+        &self,
+        options: ConnectOptions,
+    ) -> Result<Aranet4, ConnectionError> {
+        let slot = self.slot().await;
+
+        Ok(with_slot(
+            connect_with_adapter(&self.adapter, options).await?,
+            slot,
+        ))
+    }
+
+    /// Connect to a specific device, skipping the scan if the adapter already knows about it
+    pub async fn connect_to(
+        &self,
+        target: Target,
+        options: ConnectOptions,
+    ) -> Result<Aranet4, ConnectionError> {
+        let slot = self.slot().await;
+
+        Ok(with_slot(
+            connect_to(&self.adapter, target, options).await?,
+            slot,
+        ))
+    }
+
+    /// Find all of the given devices in a single scan and connect to them
+    pub async fn connect_many(
+        &self,
+        targets: &[Target],
+        options: ConnectOptions,
+    ) -> Result<Vec<Aranet4>, ConnectionError> {
+        let mut slots = Vec::with_capacity(targets.len());
+        if let Some((_, limit)) = &self.connections {
+            if targets.len() > *limit {
+                return Err(ConnectionError::ConnectionLimit {
+                    requested: targets.len(),
+                    limit: *limit,
+                });
+            }
+
+            slots.extend(self.slots(targets.len()).await.into_iter().map(Some));
+        }
+
+        let devices = connect_many(&self.adapter, targets, options).await?;
+        slots.resize_with(devices.len(), || None);
+
+        Ok(devices
+            .into_iter()
+            .zip(slots)
+            .map(|(device, slot)| with_slot(device, slot))
+            .collect())
+    }
+
+    /// Scan for Aranet devices of any supported model without a deadline, yielding each one as it is first seen
+    pub async fn discover(
+        &self,
+    ) -> Result<
+        ScanStream<impl Stream<Item = Result<DiscoveredDevice, ConnectionError>>>,
+        ConnectionError,
+    > {
+        discover(&self.adapter, self.scan_mode).await
+    }
+
+    /// Scan for Aranet devices of any supported model without a deadline, reporting each one as it
+    /// comes into range and again once it hasn't advertised for `stale_after`
+    pub async fn watch_devices(
+        &self,
+        stale_after: Duration,
+    ) -> Result<ScanStream<impl Stream<Item = DiscoveryEvent>>, ConnectionError> {
+        watch_devices(&self.adapter, stale_after, self.scan_mode).await
+    }
+
+    /// Scan for Aranet devices of any supported model for the given duration, returning every one
+    /// that was seen
+    pub async fn scan(&self, duration: Duration) -> Result<Vec<DiscoveredDevice>, ConnectionError> {
+        scan(&self.adapter, duration, self.scan_mode).await
+    }
+
+    async fn slot(&self) -> Option<Slot> {
+        self.slots(1).await.pop()
+    }
+
+    /// Take `count` slots at once, so concurrent callers can't each hold part of what they need
+    /// while waiting on the rest
+    async fn slots(&self, count: usize) -> Vec<Slot> {
+        let Some((connections, _)) = &self.connections else {
+            return Vec::new();
+        };
+
+        connections
+            .clone()
+            .acquire_many_owned(count as u32)
+            .await
+            .expect("the semaphore is never closed")
+            .forget();
+
+        (0..count).map(|_| Slot(connections.clone())).collect()
+    }
+}
+
+/// A single connection slot, given back to the manager when dropped
+pub(crate) struct Slot(Arc<Semaphore>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.add_permits(1);
+    }
+}
+
+fn with_slot(device: Aranet4, slot: Option<Slot>) -> Aranet4 {
+    match slot {
+        Some(slot) => device.with_slot(slot),
+        None => device,
+    }
+}
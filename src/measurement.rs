@@ -0,0 +1,44 @@
+use crate::{SensorData, SensorState};
+
+/// A single measured quantity, independent of the device that produced it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Measurement {
+    /// CO2 concentration in ppm
+    Co2(u16),
+    /// Temperature in Celsius
+    Temperature(f32),
+    /// Percentage of relative humidity
+    Humidity(f32),
+    /// Atmospheric pressure in hPa
+    Pressure(f32),
+    /// Percentage of battery remaining
+    Battery(u8),
+    /// Radon concentration in Bq/m³
+    Radon(u32),
+    /// Radiation dose rate in µSv/h
+    DoseRate(f32),
+    /// Accumulated radiation dose in mSv
+    Dose(f32),
+}
+
+impl From<&SensorData> for Vec<Measurement> {
+    fn from(data: &SensorData) -> Self {
+        if data.sensor_state == SensorState::Invalid {
+            return vec![Measurement::Battery(data.battery)];
+        }
+
+        vec![
+            Measurement::Co2(data.co2),
+            Measurement::Temperature(data.temperature),
+            Measurement::Humidity(data.humidity_percent()),
+            Measurement::Pressure(f32::from(data.pressure)),
+            Measurement::Battery(data.battery),
+        ]
+    }
+}
+
+impl From<SensorData> for Vec<Measurement> {
+    fn from(data: SensorData) -> Self {
+        Self::from(&data)
+    }
+}
@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use notify_rust::Notification;
+use std::collections::HashMap;
+
+use crate::{ExportError, Exporter, Status, TaggedReading};
+
+/// Raises a desktop notification whenever a device's CO2 status gets worse
+#[derive(Debug, Default)]
+pub struct NotificationExporter {
+    statuses: HashMap<String, Status>,
+}
+
+impl NotificationExporter {
+    /// Create an exporter that hasn't seen any readings yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Exporter for NotificationExporter {
+    async fn export(&mut self, readings: &[TaggedReading]) -> Result<(), ExportError> {
+        for reading in readings {
+            let status = reading.data.status;
+            let previous = self.statuses.insert(reading.device.clone(), status);
+
+            // Only alert when the status gets worse, not when it improves, like from red to amber
+            if status <= previous.unwrap_or(Status::GREEN) {
+                continue;
+            }
+
+            let summary = match status {
+                Status::NONE | Status::GREEN => continue,
+                Status::AMBER => "CO2 is getting high",
+                Status::RED => "CO2 is red, open a window",
+            };
+
+            let mut notification = Notification::new();
+            notification
+                .summary(summary)
+                .body(&format!("{}: {} ppm", reading.device, reading.data.co2));
+
+            // Showing a notification blocks on the desktop's notification service
+            tokio::task::spawn_blocking(move || notification.show().map(drop))
+                .await
+                .map_err(|err| ExportError::Other(Box::new(err)))?
+                .map_err(|err| ExportError::Other(Box::new(err)))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Instant;
+
+use crate::SensorData;
+
+/// Physical parameters of a room, used to estimate its occupancy from CO2 levels
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomParameters {
+    /// Volume of the room in m³
+    pub volume: f32,
+    /// Fresh air supplied to the room in m³/h
+    pub ventilation: f32,
+    /// CO2 concentration of the supplied air in ppm
+    pub outdoor_co2: f32,
+    /// CO2 exhaled by each occupant in m³/h
+    pub co2_per_person: f32,
+}
+
+impl RoomParameters {
+    /// Parameters for a room of the given volume and ventilation rate, with sedentary adult occupants
+    pub fn new(volume: f32, ventilation: f32) -> Self {
+        Self {
+            volume,
+            ventilation,
+            outdoor_co2: 420.0,
+            co2_per_person: 0.018,
+        }
+    }
+}
+
+/// An estimate of how many people are in a room
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Occupancy {
+    /// Estimated number of people in the room
+    pub people: f32,
+}
+
+impl Occupancy {
+    /// Whether the room is likely occupied
+    pub fn is_occupied(&self) -> bool {
+        self.people >= 0.5
+    }
+}
+
+/// Estimates occupancy from the CO2 level and its rate of change, using a single-zone mass balance
+///
+/// This is a heuristic: it assumes the air in the room is well mixed and the ventilation rate is
+/// constant, so treat the result as a rough indication.
+#[derive(Debug, Clone)]
+pub struct OccupancyEstimator {
+    room: RoomParameters,
+    last: Option<(Instant, f32)>,
+}
+
+impl OccupancyEstimator {
+    /// Create an estimator for the given room
+    pub fn new(room: RoomParameters) -> Self {
+        Self { room, last: None }
+    }
+
+    /// Add a reading taken at the given time, returning the updated estimate
+    pub fn update(&mut self, data: &SensorData, at: Instant) -> Occupancy {
+        let co2 = f32::from(data.co2);
+
+        // ppm per hour
+        let rise_rate = match self.last {
+            Some((previous_at, previous_co2)) if at > previous_at => {
+                (co2 - previous_co2) / (at - previous_at).as_secs_f32() * 3600.0
+            }
+            _ => 0.0,
+        };
+        self.last = Some((at, co2));
+
+        let generated =
+            self.room.volume * rise_rate + self.room.ventilation * (co2 - self.room.outdoor_co2);
+        let people = generated / (self.room.co2_per_person * 1_000_000.0);
+
+        Occupancy {
+            people: people.max(0.0),
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use btleplug::{
+    api::{BDAddr, ScanFilter},
+    platform::PeripheralId,
+};
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{AdapterSelector, DeviceError, ADVERTISED_SERVICE};
+
+/// Options controlling how an Aranet4 device is found and connected to
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// How long to scan for a device before giving up. `None` keeps scanning until it is found
+    pub timeout: Option<Duration>,
+    /// Which advertised names identify the device to connect to
+    pub name_filter: NameFilter,
+    /// The Bluetooth address of the device to connect to. When set, the name filter is ignored
+    pub address: Option<BDAddr>,
+    /// Which Bluetooth adapter to use, when the crate initializes the Bluetooth stack
+    pub adapter: AdapterSelector,
+    /// Whether to stop scanning once the device has been found
+    pub stop_scan: bool,
+    /// Whether the adapter or this crate picks out the Aranet devices while scanning
+    pub scan_mode: ScanMode,
+    /// Which device to connect to when several match
+    pub selection: Selection,
+    /// How long connecting and later operations on the device may take
+    pub timeouts: Timeouts,
+}
+
+impl ConnectOptions {
+    /// Change how long to scan for a device before giving up
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Keep scanning until the device is found, however long it takes
+    pub fn without_timeout(mut self) -> Self {
+        self.timeout = None;
+        self
+    }
+
+    /// Change which advertised names identify the device to connect to
+    pub fn with_name_filter(mut self, name_filter: NameFilter) -> Self {
+        self.name_filter = name_filter;
+        self
+    }
+
+    /// Only connect to devices whose name starts with `prefix`
+    pub fn with_name_prefix(self, prefix: impl Into<String>) -> Self {
+        self.with_name_filter(NameFilter::Prefix(prefix.into()))
+    }
+
+    /// Only connect to the device with the given Bluetooth address
+    pub fn with_address(mut self, address: BDAddr) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Change which Bluetooth adapter to use, when the crate initializes the Bluetooth stack
+    pub fn with_adapter(mut self, adapter: AdapterSelector) -> Self {
+        self.adapter = adapter;
+        self
+    }
+
+    /// Change whether to stop scanning once the device has been found
+    pub fn with_stop_scan(mut self, stop_scan: bool) -> Self {
+        self.stop_scan = stop_scan;
+        self
+    }
+
+    /// Change which device to connect to when several match
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Change whether the adapter or this crate picks out the Aranet devices while scanning
+    pub fn with_scan_mode(mut self, scan_mode: ScanMode) -> Self {
+        self.scan_mode = scan_mode;
+        self
+    }
+
+    /// Change how long connecting and later operations on the device may take
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub(crate) fn matches(
+        &self,
+        address: BDAddr,
+        name: Option<&str>,
+        advertises_service: bool,
+    ) -> bool {
+        match self.address {
+            Some(target) => target == address,
+            None => self.name_filter.matches(name, advertises_service),
+        }
+    }
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(10)),
+            name_filter: NameFilter::default(),
+            address: None,
+            adapter: AdapterSelector::default(),
+            stop_scan: true,
+            scan_mode: ScanMode::default(),
+            selection: Selection::default(),
+            timeouts: Timeouts::default(),
+        }
+    }
+}
+
+/// Picks which device to connect to when several match
+///
+/// Every strategy but [`Selection::First`] keeps scanning for a couple of seconds after the first
+/// match, to find the other devices in range. The scan still ends at the timeout, picking from the
+/// devices found by then.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Selection {
+    /// Whichever device is seen first
+    #[default]
+    First,
+    /// The device with the strongest signal
+    StrongestSignal,
+    /// The device whose name comes first alphabetically
+    Alphabetical,
+    /// The device with the given serial number, which requires connecting to each one to check
+    SerialNumber(String),
+    /// The only matching device, failing with [`ConnectionError::AmbiguousDevice`] if there are
+    /// several
+    ///
+    /// [`ConnectionError::AmbiguousDevice`]: crate::ConnectionError::AmbiguousDevice
+    Unique,
+}
+
+/// How devices are filtered while scanning
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScanMode {
+    /// Ask the adapter to only report devices advertising the Aranet service
+    #[default]
+    Filtered,
+    /// Scan for every device and filter them by advertised service or name here instead, for
+    /// adapters and backends that ignore scan filters
+    Unfiltered,
+}
+
+impl ScanMode {
+    pub(crate) fn filter(self) -> ScanFilter {
+        match self {
+            Self::Filtered => ScanFilter {
+                services: vec![ADVERTISED_SERVICE],
+            },
+            Self::Unfiltered => ScanFilter::default(),
+        }
+    }
+}
+
+/// Recognizes the device to connect to by its advertised name
+#[derive(Clone)]
+pub enum NameFilter {
+    /// Any device advertising the Aranet service, whatever its name
+    Any,
+    /// Devices whose name starts with the given prefix
+    Prefix(String),
+    /// Devices whose name is accepted by the given predicate
+    Predicate(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl NameFilter {
+    /// Accept the devices whose name is accepted by `predicate`
+    pub fn predicate(predicate: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self::Predicate(Arc::new(predicate))
+    }
+
+    pub(crate) fn matches(&self, name: Option<&str>, advertises_service: bool) -> bool {
+        match (self, name) {
+            (Self::Any, _) => advertises_service,
+            (Self::Prefix(prefix), Some(name)) => name.starts_with(prefix.as_str()),
+            (Self::Predicate(predicate), Some(name)) => predicate(name),
+            (_, None) => false,
+        }
+    }
+}
+
+impl Default for NameFilter {
+    fn default() -> Self {
+        Self::Prefix("Aranet4".to_string())
+    }
+}
+
+impl fmt::Debug for NameFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("Any"),
+            Self::Prefix(prefix) => f.debug_tuple("Prefix").field(prefix).finish(),
+            Self::Predicate(_) => f.write_str("Predicate(..)"),
+        }
+    }
+}
+
+/// Options controlling how the history stored on a device is downloaded
+#[derive(Debug, Clone)]
+pub struct HistoryOptions {
+    /// How many records to request at a time. Smaller chunks lose less progress when the
+    /// connection drops, at the cost of more round trips
+    pub chunk_size: u16,
+    /// How many times to try downloading a chunk before giving up
+    pub attempts: u32,
+    /// How long to watch the device's clock before the first download, to estimate how far it
+    /// drifts from the host's and correct record timestamps. Windows shorter than ten minutes are
+    /// extended, and `None` skips the correction. The estimate is reused for the lifetime of the
+    /// handle, see [`Aranet4::clock_rate`](crate::Aranet4::clock_rate)
+    pub drift_window: Option<Duration>,
+}
+
+impl Default for HistoryOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: 100,
+            attempts: 3,
+            drift_window: None,
+        }
+    }
+}
+
+/// How long individual Bluetooth operations may take before they are abandoned, so a hung
+/// adapter can't stall a call forever
+#[derive(Debug, Clone)]
+pub struct Timeouts {
+    /// Connecting to the device
+    pub connect: Duration,
+    /// Discovering the device's services and characteristics
+    pub service_discovery: Duration,
+    /// Reading a characteristic
+    pub read: Duration,
+    /// Writing to a characteristic
+    pub write: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(20),
+            service_discovery: Duration::from_secs(20),
+            read: Duration::from_secs(10),
+            write: Duration::from_secs(10),
+        }
+    }
+}
+
+/// How to retry operations that fail with a temporary error, like an ATT timeout or a race with
+/// the connection dropping
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// How many times to try an operation before giving up
+    pub attempts: u32,
+    /// How long to wait between two attempts
+    pub backoff: Duration,
+    /// Decides which errors are worth retrying
+    pub retryable: Arc<dyn Fn(&DeviceError) -> bool + Send + Sync>,
+}
+
+impl RetryPolicy {
+    /// Never retry
+    pub fn none() -> Self {
+        Self {
+            attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Only retry the errors accepted by `retryable`
+    pub fn retry_if(
+        mut self,
+        retryable: impl Fn(&DeviceError) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.retryable = Arc::new(retryable);
+        self
+    }
+
+    pub(crate) fn should_retry(&self, error: &DeviceError) -> bool {
+        (self.retryable)(error)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(250),
+            retryable: Arc::new(DeviceError::is_transient),
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("attempts", &self.attempts)
+            .field("backoff", &self.backoff)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How to reconnect when an operation finds the connection to the device has dropped
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// How many times to retry a failed reconnection before giving up
+    pub max_retries: u32,
+    /// How long to wait before the first retry. The wait doubles after each failed attempt
+    pub initial_backoff: Duration,
+    /// The longest to wait between two attempts
+    pub max_backoff: Duration,
+    /// The fraction of each wait to randomly shave off, so that several clients don't retry in
+    /// lockstep. Between 0 and 1
+    pub jitter: f64,
+}
+
+impl ReconnectPolicy {
+    /// How long to wait before the given retry, starting from 0
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+
+        // A freshly seeded hasher is a cheap source of randomness without extra dependencies
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+
+        backoff.mul_f64(1.0 - self.jitter.clamp(0.0, 1.0) * random)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.2,
+        }
+    }
+}
+
+/// Identifies a specific device to connect to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// The device's Bluetooth address
+    Address(BDAddr),
+    /// The device's advertised name, like "Aranet4 1A2B3"
+    Name(String),
+    /// The platform's identifier for the device, like its CoreBluetooth UUID on macOS
+    Id(PeripheralId),
+}
+
+impl Target {
+    pub(crate) fn matches(&self, id: &PeripheralId, address: BDAddr, name: Option<&str>) -> bool {
+        match self {
+            Self::Address(target) => *target == address,
+            Self::Name(target) => name == Some(target.as_str()),
+            Self::Id(target) => target == id,
+        }
+    }
+}
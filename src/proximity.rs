@@ -0,0 +1,81 @@
+/// Rough classification of how close a device is, based on its signal strength
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Proximity {
+    /// Within a meter or so of the adapter
+    Immediate,
+    /// Likely in the same room as the adapter
+    SameRoom,
+    /// Likely in another room, or far away
+    Distant,
+}
+
+impl Proximity {
+    /// Classify a signal strength in dBm
+    pub fn from_rssi(rssi: f32) -> Self {
+        if rssi >= -60.0 {
+            Self::Immediate
+        } else if rssi >= -80.0 {
+            Self::SameRoom
+        } else {
+            Self::Distant
+        }
+    }
+}
+
+/// Estimate the distance to a device in meters from its signal strength, using a log-distance path loss model
+///
+/// `tx_power` is the transmission power advertised by the device. The result is only a rough
+/// indication, since walls and bodies attenuate the signal.
+pub fn estimate_distance(rssi: f32, tx_power: i16) -> f32 {
+    // Free-space path loss over the first meter at 2.4 GHz
+    const REFERENCE_LOSS: f32 = 41.0;
+    const PATH_LOSS_EXPONENT: f32 = 2.0;
+
+    let reference_rssi = f32::from(tx_power) - REFERENCE_LOSS;
+
+    10f32.powf((reference_rssi - rssi) / (10.0 * PATH_LOSS_EXPONENT))
+}
+
+/// Exponentially smoothed signal strength, to even out the jitter between individual samples
+#[derive(Debug, Clone)]
+pub struct RssiTracker {
+    smoothing: f32,
+    value: Option<f32>,
+}
+
+impl RssiTracker {
+    /// Create a tracker where each new sample contributes `smoothing` (between 0 and 1) to the average
+    pub fn new(smoothing: f32) -> Self {
+        Self {
+            smoothing: smoothing.clamp(0.0, 1.0),
+            value: None,
+        }
+    }
+
+    /// Add a new sample in dBm, returning the smoothed value
+    pub fn update(&mut self, rssi: i16) -> f32 {
+        let rssi = f32::from(rssi);
+        let value = self
+            .value
+            .map_or(rssi, |value| value + self.smoothing * (rssi - value));
+
+        self.value = Some(value);
+        value
+    }
+
+    /// The smoothed signal strength in dBm, if any samples were added
+    pub fn rssi(&self) -> Option<f32> {
+        self.value
+    }
+
+    /// The proximity classification of the smoothed signal strength
+    pub fn proximity(&self) -> Option<Proximity> {
+        self.value.map(Proximity::from_rssi)
+    }
+}
+
+impl Default for RssiTracker {
+    fn default() -> Self {
+        Self::new(0.3)
+    }
+}
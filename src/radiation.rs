@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+use btleplug::{api, platform::Peripheral};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::{
+    io::Cursor,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    history::{Parameter, StoredRecord},
+    Aranet4, Capabilities, ConnectionError, DeviceError, Info, Measurement, Sensor, Timeouts,
+    SENSOR_READINGS_CHARACTERISTIC,
+};
+
+/// Measurements from an Aranet Radiation device
+#[derive(Debug, Clone)]
+pub struct RadiationData {
+    /// Radiation dose rate in µSv/h
+    pub dose_rate: f32,
+    /// Accumulated radiation dose in mSv
+    pub dose: f32,
+    /// How long the dose has been accumulating for
+    pub dose_duration: Duration,
+    /// Percentage of battery remaining
+    pub battery: u8,
+    /// Measurement interval
+    pub interval: Duration,
+    /// Time since last update
+    pub since_last_update: Duration,
+}
+
+impl From<&RadiationData> for Vec<Measurement> {
+    fn from(data: &RadiationData) -> Self {
+        vec![
+            Measurement::DoseRate(data.dose_rate),
+            Measurement::Dose(data.dose),
+            Measurement::Battery(data.battery),
+        ]
+    }
+}
+
+impl From<RadiationData> for Vec<Measurement> {
+    fn from(data: RadiationData) -> Self {
+        Self::from(&data)
+    }
+}
+
+/// A measurement stored on an Aranet Radiation device
+#[derive(Debug, Clone)]
+pub struct RadiationRecord {
+    /// When the measurement was taken, corrected for the drift of the device's clock
+    pub timestamp: SystemTime,
+    /// When the measurement was taken, assuming the device's clock keeps perfect time
+    pub raw_timestamp: SystemTime,
+    /// Radiation dose rate in µSv/h
+    pub dose_rate: f32,
+    /// Radiation dose received during the measurement interval in mSv
+    pub dose: f32,
+}
+
+impl StoredRecord for RadiationRecord {
+    const PARAMETERS: &'static [Parameter] =
+        &[Parameter::RadiationDoseRate, Parameter::RadiationDose];
+
+    fn from_values(timestamp: SystemTime, raw_timestamp: SystemTime, values: &[u32]) -> Self {
+        Self {
+            timestamp,
+            raw_timestamp,
+            dose_rate: values[0] as f32 / 1000.0,
+            dose: values[1] as f32 / 1_000_000.0,
+        }
+    }
+}
+
+/// A connected Aranet Radiation monitor
+pub struct Radiation<P: api::Peripheral = Peripheral> {
+    device: Aranet4<P>,
+}
+
+impl<P: api::Peripheral> Radiation<P> {
+    /// Wrap an already connected peripheral
+    pub fn new(device: P) -> Result<Self, ConnectionError> {
+        Ok(Self {
+            device: Aranet4::for_readings(device, SENSOR_READINGS_CHARACTERISTIC)?,
+        })
+    }
+
+    /// Abandon Bluetooth operations that take longer than the given timeouts
+    pub fn with_timeouts(self, timeouts: Timeouts) -> Self {
+        Self {
+            device: self.device.with_timeouts(timeouts),
+        }
+    }
+
+    /// Get the device information
+    pub async fn info(&self) -> Result<Info, DeviceError> {
+        self.device.info().await
+    }
+
+    /// Get the current measurements from the device
+    pub async fn measurements(&self) -> Result<RadiationData, DeviceError> {
+        let mut payload = Cursor::new(self.device.read(SENSOR_READINGS_CHARACTERISTIC).await?);
+
+        let _device_type = payload.read_u16::<LittleEndian>()?;
+        let interval = payload.read_u16::<LittleEndian>()?;
+        let since_last_update = payload.read_u16::<LittleEndian>()?;
+        let battery = payload.read_u8()?;
+        // Reported in nSv/h and nSv
+        let dose_rate = payload.read_u32::<LittleEndian>()? as f32 / 1000.0;
+        let dose = payload.read_u64::<LittleEndian>()? as f32 / 1_000_000.0;
+        let dose_duration = payload.read_u32::<LittleEndian>()?;
+
+        Ok(RadiationData {
+            dose_rate,
+            dose,
+            dose_duration: Duration::from_secs(u64::from(dose_duration)),
+            battery,
+            interval: Duration::from_secs(u64::from(interval)),
+            since_last_update: Duration::from_secs(u64::from(since_last_update)),
+        })
+    }
+
+    /// Download all the measurements stored on the device
+    pub async fn history(&self) -> Result<Vec<RadiationRecord>, DeviceError> {
+        self.device.stored_history().await
+    }
+
+    /// Detect the features supported by the device
+    pub async fn capabilities(&self) -> Result<Capabilities, DeviceError> {
+        self.device.capabilities().await
+    }
+
+    /// Disconnect from the device
+    pub async fn disconnect(&self) -> Result<(), DeviceError> {
+        self.device.disconnect().await
+    }
+}
+
+#[async_trait]
+impl<P: api::Peripheral> Sensor for Radiation<P> {
+    type Reading = RadiationData;
+    type Record = RadiationRecord;
+
+    async fn info(&self) -> Result<Info, DeviceError> {
+        Radiation::info(self).await
+    }
+
+    async fn read(&self) -> Result<RadiationData, DeviceError> {
+        self.measurements().await
+    }
+
+    async fn history(&self) -> Result<Vec<RadiationRecord>, DeviceError> {
+        Radiation::history(self).await
+    }
+
+    async fn capabilities(&self) -> Result<Capabilities, DeviceError> {
+        Radiation::capabilities(self).await
+    }
+}
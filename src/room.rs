@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use crate::SensorData;
+
+// The Aranet4's stated accuracy for each metric, the smallest deviation two devices in the same
+// room can be expected to show
+const CO2_ACCURACY: f32 = 30.0;
+const TEMPERATURE_ACCURACY: f32 = 0.3;
+const HUMIDITY_ACCURACY: f32 = 3.0;
+const PRESSURE_ACCURACY: f32 = 1.0;
+
+/// A consensus reading for a room instrumented with one or more devices
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomReading {
+    /// CO2 concentration in ppm
+    pub co2: f32,
+    /// Temperature in Celsius
+    pub temperature: f32,
+    /// Percentage of relative humidity
+    pub humidity: f32,
+    /// Atmospheric pressure in hPa
+    pub pressure: f32,
+    /// Number of devices that contributed to the reading
+    pub devices: usize,
+}
+
+/// Merges the latest readings of devices grouped by room into a consensus value
+///
+/// For each metric, values further than `threshold` median absolute deviations from the median
+/// are rejected as outliers, and the remaining ones are averaged. The deviation is never taken to
+/// be smaller than the sensor's stated accuracy, so devices that agree exactly don't make every
+/// other value an outlier.
+#[derive(Debug, Clone)]
+pub struct RoomAggregator {
+    threshold: f32,
+    rooms: HashMap<String, String>,
+    readings: HashMap<String, SensorData>,
+}
+
+impl RoomAggregator {
+    /// Create an aggregator rejecting values further than `threshold` deviations from the median
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            threshold,
+            rooms: HashMap::new(),
+            readings: HashMap::new(),
+        }
+    }
+
+    /// Assign a device (by any stable identifier, like its serial number) to a room
+    pub fn assign(&mut self, device: impl Into<String>, room: impl Into<String>) {
+        self.rooms.insert(device.into(), room.into());
+    }
+
+    /// Record the latest reading of a device
+    pub fn update(&mut self, device: impl Into<String>, data: SensorData) {
+        self.readings.insert(device.into(), data);
+    }
+
+    /// Get the consensus reading for a room, if any of its devices have reported
+    pub fn reading(&self, room: &str) -> Option<RoomReading> {
+        let readings = self
+            .readings
+            .iter()
+            .filter(|(device, _)| self.rooms.get(*device).is_some_and(|r| r == room))
+            .map(|(_, data)| data)
+            .collect::<Vec<_>>();
+
+        if readings.is_empty() {
+            return None;
+        }
+
+        let metric = |f: fn(&SensorData) -> f32, accuracy: f32| {
+            consensus(
+                readings.iter().map(|data| f(data)).collect(),
+                self.threshold,
+                accuracy,
+            )
+        };
+
+        Some(RoomReading {
+            co2: metric(|data| f32::from(data.co2), CO2_ACCURACY),
+            temperature: metric(|data| data.temperature, TEMPERATURE_ACCURACY),
+            humidity: metric(SensorData::humidity_percent, HUMIDITY_ACCURACY),
+            pressure: metric(|data| f32::from(data.pressure), PRESSURE_ACCURACY),
+            devices: readings.len(),
+        })
+    }
+}
+
+impl Default for RoomAggregator {
+    fn default() -> Self {
+        Self::new(3.0)
+    }
+}
+
+fn consensus(values: Vec<f32>, threshold: f32, accuracy: f32) -> f32 {
+    let center = median(values.clone());
+    let deviation = median(values.iter().map(|v| (v - center).abs()).collect()).max(accuracy);
+
+    let inliers = values
+        .into_iter()
+        .filter(|v| (v - center).abs() <= threshold * deviation)
+        .collect::<Vec<_>>();
+
+    inliers.iter().sum::<f32>() / inliers.len() as f32
+}
+
+fn median(mut values: Vec<f32>) -> f32 {
+    values.sort_by(f32::total_cmp);
+
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(vec![3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(vec![4.0, 1.0, 3.0, 2.0]), 2.5);
+    }
+
+    #[test]
+    fn rejects_outliers() {
+        assert_eq!(
+            consensus(vec![400.0, 410.0, 420.0, 2000.0], 3.0, 30.0),
+            410.0
+        );
+    }
+
+    #[test]
+    fn keeps_values_close_to_the_median() {
+        assert_eq!(consensus(vec![400.0, 410.0, 420.0], 3.0, 30.0), 410.0);
+    }
+
+    #[test]
+    fn rejects_outliers_when_most_values_agree() {
+        assert_eq!(
+            consensus(vec![400.0, 400.0, 400.0, 2000.0], 3.0, 30.0),
+            400.0
+        );
+    }
+
+    #[test]
+    fn rejects_a_single_outlier_among_three() {
+        assert_eq!(consensus(vec![400.0, 400.0, 2000.0], 3.0, 30.0), 400.0);
+    }
+
+    #[test]
+    fn keeps_values_within_the_sensor_accuracy() {
+        assert_eq!(consensus(vec![20.0, 20.0, 20.0, 20.5], 3.0, 0.3), 20.125);
+    }
+
+    #[test]
+    fn keeps_identical_values() {
+        assert_eq!(consensus(vec![400.0, 400.0], 3.0, 30.0), 400.0);
+    }
+}
@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+use btleplug::api::Peripheral;
+
+use crate::{Aranet4, Capabilities, DeviceError, HistoryRecord, Info, Measurement, SensorData};
+
+/// A common interface over the different kinds of Aranet devices
+///
+/// The associated reading and record types make this usable for writing code generic over the
+/// device model, but not as a trait object. To handle a fleet of mixed models through one type,
+/// use [`AranetDevice`](crate::AranetDevice), which wraps any of them and reports readings as
+/// [`Measurement`]s.
+#[async_trait]
+pub trait Sensor: Sync {
+    /// The readings produced by this kind of device
+    type Reading: Into<Vec<Measurement>> + Send;
+
+    /// The measurements stored in the device's history
+    type Record: Send;
+
+    /// Get the device information
+    async fn info(&self) -> Result<Info, DeviceError>;
+
+    /// Get the current readings from the device
+    async fn read(&self) -> Result<Self::Reading, DeviceError>;
+
+    /// Download the measurements stored on the device
+    async fn history(&self) -> Result<Vec<Self::Record>, DeviceError>;
+
+    /// Detect the features supported by the device
+    async fn capabilities(&self) -> Result<Capabilities, DeviceError>;
+
+    /// Get the current readings from the device as a list of measurements
+    async fn read_measurements(&self) -> Result<Vec<Measurement>, DeviceError> {
+        Ok(self.read().await?.into())
+    }
+}
+
+#[async_trait]
+impl<P: Peripheral> Sensor for Aranet4<P> {
+    type Reading = SensorData;
+    type Record = HistoryRecord;
+
+    async fn info(&self) -> Result<Info, DeviceError> {
+        Aranet4::info(self).await
+    }
+
+    async fn read(&self) -> Result<SensorData, DeviceError> {
+        self.measurements().await
+    }
+
+    async fn history(&self) -> Result<Vec<HistoryRecord>, DeviceError> {
+        Aranet4::history(self).await
+    }
+
+    async fn capabilities(&self) -> Result<Capabilities, DeviceError> {
+        Aranet4::capabilities(self).await
+    }
+}
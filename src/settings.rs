@@ -0,0 +1,74 @@
+use btleplug::api::Peripheral;
+use std::{collections::BTreeMap, time::Duration};
+use uuid::{uuid, Uuid};
+
+use crate::{Aranet4, DeviceError, COMMAND_CHARACTERISTIC};
+
+pub(crate) const SETTINGS_CHARACTERISTIC: Uuid = uuid!("f0cd1401-95da-4f4b-9ac8-aa55d312af0c");
+
+// Command bytes as sent by `Aranet4.set_interval`, `Aranet4.set_integrations` and
+// `Aranet4.set_range` in Aranet4-Python's client.py, followed by a single argument byte
+const SET_INTERVAL_COMMAND: u8 = 0x90;
+const SET_SMART_HOME_COMMAND: u8 = 0x91;
+const SET_RANGE_COMMAND: u8 = 0x92;
+/// Measurement intervals supported by the device, in minutes
+const SUPPORTED_INTERVALS: [u8; 4] = [1, 2, 5, 10];
+
+impl<P: Peripheral> Aranet4<P> {
+    /// Get the undecoded settings registers, keyed by their byte offset
+    pub async fn raw_settings(&self) -> Result<BTreeMap<u8, u8>, DeviceError> {
+        self.require(SETTINGS_CHARACTERISTIC, "settings")?;
+        let data = self.read(SETTINGS_CHARACTERISTIC).await?;
+
+        Ok((0..=u8::MAX).zip(data).collect())
+    }
+
+    /// Enable or disable Smart Home integration, which makes the device include its readings in
+    /// its Bluetooth advertisements
+    pub async fn set_smart_home_integration(&self, enabled: bool) -> Result<(), DeviceError> {
+        if !self.capabilities().await?.smart_home_integration {
+            return Err(DeviceError::Unsupported("Smart Home integration"));
+        }
+
+        self.write_flag(SET_SMART_HOME_COMMAND, enabled).await
+    }
+
+    /// Enable or disable Bluetooth extended range mode
+    pub async fn set_extended_range(&self, enabled: bool) -> Result<(), DeviceError> {
+        self.write_flag(SET_RANGE_COMMAND, enabled).await
+    }
+
+    /// Change how often the device takes a measurement, which must be 1, 2, 5 or 10 minutes
+    ///
+    /// Note that the device clears its stored history when the interval changes.
+    pub async fn set_interval(&self, interval: Duration) -> Result<(), DeviceError> {
+        let minutes = SUPPORTED_INTERVALS
+            .into_iter()
+            .find(|&minutes| Duration::from_secs(u64::from(minutes) * 60) == interval)
+            .ok_or(DeviceError::UnsupportedInterval(interval))?;
+
+        self.write_raw_command_unchecked(SET_INTERVAL_COMMAND, &[minutes])
+            .await
+    }
+
+    /// Send a raw command to the device, made of a command byte followed by its arguments
+    ///
+    /// Nothing about the command is validated, and writing unknown commands may leave the device
+    /// misconfigured. Prefer the typed APIs where they exist.
+    pub async fn write_raw_command_unchecked(
+        &self,
+        command: u8,
+        value: &[u8],
+    ) -> Result<(), DeviceError> {
+        let mut payload = Vec::with_capacity(value.len() + 1);
+        payload.push(command);
+        payload.extend_from_slice(value);
+
+        self.write(COMMAND_CHARACTERISTIC, &payload).await
+    }
+
+    async fn write_flag(&self, command: u8, enabled: bool) -> Result<(), DeviceError> {
+        self.write_raw_command_unchecked(command, &[u8::from(enabled)])
+            .await
+    }
+}
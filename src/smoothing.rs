@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use crate::{Metric, SensorData};
+
+/// A filter smoothing a series of values
+pub trait Smoother: Send {
+    /// Add a new value, returning the smoothed one
+    fn update(&mut self, value: f32) -> f32;
+}
+
+/// Exponential moving average
+#[derive(Debug, Clone)]
+pub struct Ema {
+    alpha: f32,
+    value: Option<f32>,
+}
+
+impl Ema {
+    /// Create an average where each new value contributes `alpha` (between 0 and 1) to the result
+    pub fn new(alpha: f32) -> Self {
+        Self {
+            alpha: alpha.clamp(0.0, 1.0),
+            value: None,
+        }
+    }
+}
+
+impl Smoother for Ema {
+    fn update(&mut self, value: f32) -> f32 {
+        let smoothed = self
+            .value
+            .map_or(value, |current| current + self.alpha * (value - current));
+
+        self.value = Some(smoothed);
+        smoothed
+    }
+}
+
+/// One-dimensional Kalman filter for a slowly changing value
+#[derive(Debug, Clone)]
+pub struct Kalman {
+    process_noise: f32,
+    measurement_noise: f32,
+    estimate: Option<f32>,
+    error: f32,
+}
+
+impl Kalman {
+    /// Create a filter given how much the real value varies between samples (`process_noise`)
+    /// and how noisy the sensor is (`measurement_noise`), both as variances
+    pub fn new(process_noise: f32, measurement_noise: f32) -> Self {
+        Self {
+            process_noise,
+            measurement_noise,
+            estimate: None,
+            error: measurement_noise,
+        }
+    }
+}
+
+impl Smoother for Kalman {
+    fn update(&mut self, value: f32) -> f32 {
+        let Some(estimate) = self.estimate else {
+            self.estimate = Some(value);
+            return value;
+        };
+
+        let error = self.error + self.process_noise;
+        let gain = error / (error + self.measurement_noise);
+        let estimate = estimate + gain * (value - estimate);
+
+        self.error = (1.0 - gain) * error;
+        self.estimate = Some(estimate);
+        estimate
+    }
+}
+
+/// Applies a smoother to each configured metric of a stream of readings
+#[derive(Default)]
+pub struct ReadingSmoother {
+    smoothers: HashMap<Metric, Box<dyn Smoother>>,
+}
+
+impl ReadingSmoother {
+    /// Create a smoother that leaves every metric untouched until configured
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Smooth the given metric with the given filter
+    pub fn with(mut self, metric: Metric, smoother: impl Smoother + 'static) -> Self {
+        self.smoothers.insert(metric, Box::new(smoother));
+        self
+    }
+
+    /// Pass a reading through the configured filters
+    pub fn smooth(&mut self, mut data: SensorData) -> SensorData {
+        for (metric, smoother) in &mut self.smoothers {
+            match metric {
+                Metric::Co2 => data.co2 = smoother.update(f32::from(data.co2)).round() as u16,
+                Metric::Temperature => data.temperature = smoother.update(data.temperature),
+                Metric::Humidity => {
+                    let humidity = smoother.update(data.humidity_percent());
+                    data.humidity = humidity.round() as u8;
+                    data.humidity_precise = data.humidity_precise.map(|_| humidity);
+                }
+                Metric::Pressure => {
+                    data.pressure = smoother.update(f32::from(data.pressure)).round() as u16
+                }
+            }
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::reading;
+
+    #[test]
+    fn ema_starts_at_the_first_value() {
+        let mut ema = Ema::new(0.5);
+
+        assert_eq!(ema.update(10.0), 10.0);
+        assert_eq!(ema.update(20.0), 15.0);
+        assert_eq!(ema.update(20.0), 17.5);
+    }
+
+    #[test]
+    fn kalman_converges_towards_a_constant_value() {
+        let mut kalman = Kalman::new(0.01, 1.0);
+        kalman.update(0.0);
+
+        let mut estimate = 0.0;
+        for _ in 0..100 {
+            estimate = kalman.update(10.0);
+        }
+
+        assert!((estimate - 10.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn smooths_only_the_configured_metrics() {
+        let mut smoother = ReadingSmoother::new().with(Metric::Co2, Ema::new(0.5));
+
+        smoother.smooth(SensorData {
+            co2: 600,
+            ..reading()
+        });
+        let data = smoother.smooth(SensorData {
+            co2: 800,
+            temperature: 30.0,
+            ..reading()
+        });
+
+        assert_eq!(data.co2, 700);
+        assert_eq!(data.temperature, 30.0);
+    }
+
+    #[test]
+    fn keeps_precise_humidity_in_step() {
+        let mut smoother = ReadingSmoother::new().with(Metric::Humidity, Ema::new(0.5));
+
+        smoother.smooth(SensorData {
+            humidity: 40,
+            humidity_precise: Some(40.0),
+            ..reading()
+        });
+        let data = smoother.smooth(SensorData {
+            humidity: 43,
+            humidity_precise: Some(42.6),
+            ..reading()
+        });
+
+        assert_eq!(data.humidity_precise, Some(41.3));
+        assert_eq!(data.humidity, 41);
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Counters for the GATT operations performed on a device
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of successful characteristic reads
+    pub reads: u64,
+    /// Number of successful characteristic writes
+    pub writes: u64,
+    /// Number of notifications received
+    pub notifications: u64,
+    /// Number of failed reads and writes
+    pub failures: u64,
+    /// Total number of bytes read, written and received through notifications
+    pub bytes_transferred: u64,
+    /// Average time taken by a read or write, including failed ones
+    pub average_latency: Duration,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StatsRecorder {
+    reads: AtomicU64,
+    writes: AtomicU64,
+    notifications: AtomicU64,
+    failures: AtomicU64,
+    bytes_transferred: AtomicU64,
+    operations: AtomicU64,
+    total_latency_micros: AtomicU64,
+}
+
+impl StatsRecorder {
+    pub(crate) fn record_read<T>(&self, latency: Duration, result: &Result<Vec<u8>, T>) {
+        match result {
+            Ok(data) => {
+                self.reads.fetch_add(1, Ordering::Relaxed);
+                self.bytes_transferred
+                    .fetch_add(data.len() as u64, Ordering::Relaxed);
+            }
+            Err(_) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        self.record_latency(latency);
+    }
+
+    pub(crate) fn record_write<T>(&self, latency: Duration, len: usize, result: &Result<(), T>) {
+        match result {
+            Ok(()) => {
+                self.writes.fetch_add(1, Ordering::Relaxed);
+                self.bytes_transferred
+                    .fetch_add(len as u64, Ordering::Relaxed);
+            }
+            Err(_) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        self.record_latency(latency);
+    }
+
+    pub(crate) fn record_notification(&self, len: usize) {
+        self.notifications.fetch_add(1, Ordering::Relaxed);
+        self.bytes_transferred
+            .fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    fn record_latency(&self, latency: Duration) {
+        self.operations.fetch_add(1, Ordering::Relaxed);
+        self.total_latency_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> Stats {
+        let operations = self.operations.load(Ordering::Relaxed);
+        let total_latency = self.total_latency_micros.load(Ordering::Relaxed);
+
+        Stats {
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            notifications: self.notifications.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            bytes_transferred: self.bytes_transferred.load(Ordering::Relaxed),
+            average_latency: Duration::from_micros(
+                total_latency.checked_div(operations).unwrap_or(0),
+            ),
+        }
+    }
+}
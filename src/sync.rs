@@ -0,0 +1,189 @@
+use btleplug::api::Peripheral;
+use std::{
+    ops::Bound,
+    time::{Duration, SystemTime},
+};
+
+use crate::{history::HistoryRecord, Aranet4, DeviceError, SERIAL_NUMBER_CHARACTERISTIC};
+
+/// Where a previous history sync left off, to be persisted between syncs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryCursor {
+    /// Serial number of the device the cursor belongs to
+    pub serial_number: String,
+    /// Index (1-based, oldest first) of the last synced record
+    pub last_index: u16,
+    /// When the last synced record was taken, only used once the device's memory is full and the
+    /// indices stop growing
+    pub last_timestamp: SystemTime,
+}
+
+impl<P: Peripheral> Aranet4<P> {
+    /// Download the measurements taken since the last sync, returning them along with an updated
+    /// cursor
+    ///
+    /// Everything stored on the device is downloaded when there's no cursor, when the cursor
+    /// belongs to a different device, or when the device's history was cleared since.
+    pub async fn sync_history(
+        &self,
+        cursor: Option<&HistoryCursor>,
+    ) -> Result<(Vec<HistoryRecord>, HistoryCursor), DeviceError> {
+        let serial_number = String::from_utf8(self.read(SERIAL_NUMBER_CHARACTERISTIC).await?)?;
+        let total = self.total_readings().await?;
+
+        let cursor = cursor.filter(|cursor| cursor.serial_number == serial_number);
+
+        let (records, last_index) = match cursor {
+            Some(cursor) if total > cursor.last_index && cursor.last_index > 0 => {
+                // Download the last synced record again, to check it is still at the same index
+                let mut records = self
+                    .history_page(cursor.last_index, total - cursor.last_index + 1)
+                    .await?;
+                let interval = self.interval().await?;
+
+                if records.first().is_some_and(|record| {
+                    same_record(record.timestamp, cursor.last_timestamp, interval)
+                }) {
+                    records.remove(0);
+                    let last_index = cursor.last_index + records.len() as u16;
+
+                    (records, last_index)
+                } else {
+                    // The memory filled up and started pushing out the oldest records since the
+                    // last sync, so the indices shifted
+                    (self.records_after(cursor, interval).await?, total)
+                }
+            }
+            Some(cursor) if total == cursor.last_index && cursor.last_index > 0 => {
+                let interval = self.interval().await?;
+
+                (self.records_after(cursor, interval).await?, total)
+            }
+            _ => {
+                let records = self.history().await?;
+                let last_index = records.len() as u16;
+
+                (records, last_index)
+            }
+        };
+
+        let last_timestamp = records
+            .last()
+            .map(|record| record.timestamp)
+            .or(cursor.map(|cursor| cursor.last_timestamp))
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        Ok((
+            records,
+            HistoryCursor {
+                serial_number,
+                last_index,
+                last_timestamp,
+            },
+        ))
+    }
+
+    /// Download the records taken after the last one the cursor synced, going by their timestamps
+    ///
+    /// Once the memory is full each new record pushes out the oldest, so the indices can't be
+    /// relied on. Timestamps are derived from the device's clock and can shift slightly between
+    /// syncs, so skip half an interval past the last record to avoid downloading it again.
+    async fn records_after(
+        &self,
+        cursor: &HistoryCursor,
+        interval: Duration,
+    ) -> Result<Vec<HistoryRecord>, DeviceError> {
+        self.history_range((
+            Bound::Excluded(cursor.last_timestamp + interval / 2),
+            Bound::Unbounded,
+        ))
+        .await
+    }
+
+    /// Delete the measurements stored on the device, once `cursor` shows every one of them has
+    /// been synced, returning how many were deleted
+    ///
+    /// There's no dedicated command for this, so the measurement interval is briefly changed and
+    /// restored, which makes the device clear its history. If the original interval can't be
+    /// restored, this fails with [`DeviceError::IntervalNotRestored`].
+    pub async fn clear_history(&self, cursor: &HistoryCursor) -> Result<u16, DeviceError> {
+        if !self.capabilities().await?.history {
+            return Err(DeviceError::Unsupported("history"));
+        }
+
+        let serial_number = String::from_utf8(self.read(SERIAL_NUMBER_CHARACTERISTIC).await?)?;
+        let stored = self.total_readings().await?;
+        let synced = if cursor.serial_number == serial_number {
+            cursor.last_index
+        } else {
+            0
+        };
+
+        if synced != stored {
+            return Err(DeviceError::UnsyncedHistory { synced, stored });
+        }
+
+        // Once the memory is full the count stays put as new records push out the oldest, so also
+        // check that the latest record is the one the cursor ends on
+        let interval = self.interval().await?;
+        if stored > 0 {
+            let latest = SystemTime::now() - self.since_last_update().await?;
+            let offset = match latest.duration_since(cursor.last_timestamp) {
+                Ok(newer) => newer.as_secs_f64(),
+                Err(err) => -err.duration().as_secs_f64(),
+            };
+            let unsynced = (offset / interval.as_secs_f64()).round();
+
+            if unsynced != 0.0 {
+                // A cursor ending after the device's latest record doesn't belong to this history
+                let synced = if unsynced > 0.0 {
+                    stored.saturating_sub(unsynced as u16)
+                } else {
+                    0
+                };
+
+                return Err(DeviceError::UnsyncedHistory { synced, stored });
+            }
+        }
+
+        let other = if interval == Duration::from_secs(60) {
+            Duration::from_secs(120)
+        } else {
+            Duration::from_secs(60)
+        };
+        self.set_interval(other).await?;
+        if let Err(err) = self.retrying(|| self.set_interval(interval)).await {
+            return Err(DeviceError::IntervalNotRestored {
+                interval,
+                source: Box::new(err),
+            });
+        }
+
+        Ok(stored.saturating_sub(self.total_readings().await?))
+    }
+}
+
+/// Whether two timestamps derived for a record in different syncs are for the same measurement
+fn same_record(a: SystemTime, b: SystemTime, interval: Duration) -> bool {
+    let offset = a.duration_since(b).unwrap_or_else(|err| err.duration());
+
+    offset < interval / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn matches_records_within_half_an_interval() {
+        let taken = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        assert!(same_record(taken, taken, 5 * MINUTE));
+        assert!(same_record(taken + MINUTE, taken, 5 * MINUTE));
+        assert!(same_record(taken - MINUTE, taken, 5 * MINUTE));
+        assert!(!same_record(taken + 5 * MINUTE, taken, 5 * MINUTE));
+        assert!(!same_record(taken - 5 * MINUTE, taken, 5 * MINUTE));
+    }
+}
@@ -0,0 +1,117 @@
+use std::ops::RangeInclusive;
+
+use crate::SensorData;
+
+/// A quantity measured by the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Metric {
+    /// CO2 concentration
+    Co2,
+    /// Temperature
+    Temperature,
+    /// Relative humidity
+    Humidity,
+    /// Atmospheric pressure
+    Pressure,
+}
+
+/// Plausible ranges for each metric, used to flag or reject garbage readings
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationRules {
+    /// Plausible CO2 concentration in ppm
+    pub co2: RangeInclusive<u16>,
+    /// Plausible temperature in Celsius
+    pub temperature: RangeInclusive<f32>,
+    /// Plausible percentage of relative humidity
+    pub humidity: RangeInclusive<u8>,
+    /// Plausible atmospheric pressure in hPa
+    pub pressure: RangeInclusive<u16>,
+}
+
+/// A reading rejected by [`ValidationRules::validate`]
+#[derive(Debug, thiserror::Error)]
+#[error("The reading has implausible values for {metrics:?}")]
+pub struct ImplausibleReading {
+    /// The metrics that were out of range
+    pub metrics: Vec<Metric>,
+    /// The rejected reading
+    pub data: SensorData,
+}
+
+impl ValidationRules {
+    /// Get the metrics of a reading that fall outside of their plausible range
+    pub fn check(&self, data: &SensorData) -> Vec<Metric> {
+        let mut metrics = Vec::new();
+
+        if !self.co2.contains(&data.co2) {
+            metrics.push(Metric::Co2);
+        }
+        if !self.temperature.contains(&data.temperature) {
+            metrics.push(Metric::Temperature);
+        }
+        if !self.humidity.contains(&data.humidity) {
+            metrics.push(Metric::Humidity);
+        }
+        if !self.pressure.contains(&data.pressure) {
+            metrics.push(Metric::Pressure);
+        }
+
+        metrics
+    }
+
+    /// Pass the reading through if it is plausible, or reject it
+    pub fn validate(&self, data: SensorData) -> Result<SensorData, ImplausibleReading> {
+        let metrics = self.check(&data);
+
+        if metrics.is_empty() {
+            Ok(data)
+        } else {
+            Err(ImplausibleReading { metrics, data })
+        }
+    }
+}
+
+impl Default for ValidationRules {
+    fn default() -> Self {
+        Self {
+            co2: 0..=10_000,
+            temperature: -40.0..=85.0,
+            humidity: 0..=100,
+            pressure: 300..=1_100,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::reading;
+
+    #[test]
+    fn accepts_plausible_readings() {
+        assert!(ValidationRules::default().validate(reading()).is_ok());
+    }
+
+    #[test]
+    fn reports_every_implausible_metric() {
+        let data = SensorData {
+            co2: 20_000,
+            humidity: 120,
+            ..reading()
+        };
+
+        let rejected = ValidationRules::default().validate(data).unwrap_err();
+        assert_eq!(rejected.metrics, [Metric::Co2, Metric::Humidity]);
+    }
+
+    #[test]
+    fn range_bounds_are_inclusive() {
+        let data = SensorData {
+            temperature: 85.0,
+            pressure: 300,
+            ..reading()
+        };
+
+        assert!(ValidationRules::default().check(&data).is_empty());
+    }
+}
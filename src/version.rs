@@ -0,0 +1,95 @@
+use std::{fmt, str::FromStr};
+
+/// A firmware version, as reported by the device (e.g. `v1.4.14`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    /// The major version number
+    pub major: u16,
+    /// The minor version number
+    pub minor: u16,
+    /// The patch version number
+    pub patch: u16,
+}
+
+/// Error returned when a firmware version string can't be parsed
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid firmware version: {0}")]
+pub struct ParseVersionError(String);
+
+impl Version {
+    /// Create a new version from its components
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = ParseVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim().trim_end_matches('\0');
+        let trimmed = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+
+        let mut parts = trimmed.split('.').map(str::parse::<u16>);
+        let mut next = || {
+            parts
+                .next()
+                .transpose()
+                .map_err(|_| ParseVersionError(s.to_string()))
+        };
+
+        let major = next()?.ok_or_else(|| ParseVersionError(s.to_string()))?;
+        let minor = next()?.unwrap_or(0);
+        let patch = next()?.unwrap_or(0);
+
+        if next()?.is_some() {
+            return Err(ParseVersionError(s.to_string()));
+        }
+
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_firmware_revisions() {
+        assert_eq!(
+            "v1.4.14".parse::<Version>().unwrap(),
+            Version::new(1, 4, 14)
+        );
+        assert_eq!("1.2".parse::<Version>().unwrap(), Version::new(1, 2, 0));
+        assert_eq!(" V2\0\0".parse::<Version>().unwrap(), Version::new(2, 0, 0));
+    }
+
+    #[test]
+    fn rejects_malformed_revisions() {
+        assert!("".parse::<Version>().is_err());
+        assert!("v1.x".parse::<Version>().is_err());
+        assert!("1.2.3.4".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn orders_by_component() {
+        assert!(Version::new(1, 4, 14) > Version::new(1, 4, 9));
+        assert!(Version::new(1, 10, 0) > Version::new(1, 9, 99));
+        assert!(Version::new(2, 0, 0) > Version::new(1, 99, 99));
+    }
+
+    #[test]
+    fn displays_with_prefix() {
+        assert_eq!(Version::new(1, 4, 14).to_string(), "v1.4.14");
+    }
+}
@@ -0,0 +1,56 @@
+use btleplug::api::Peripheral;
+use futures::{stream, Stream};
+use std::time::{Duration, SystemTime};
+
+use crate::{Aranet4, DeviceError, SensorData, Status};
+
+/// A change in the CO2 status displayed by the device
+#[derive(Debug, Clone)]
+pub struct StatusChange {
+    /// The previous status
+    pub from: Status,
+    /// The new status
+    pub to: Status,
+    /// When the change was observed
+    pub at: SystemTime,
+    /// The reading that triggered the change
+    pub reading: SensorData,
+}
+
+impl<P: Peripheral> Aranet4<P> {
+    /// Poll the device every `interval`, yielding only changes in its CO2 status
+    ///
+    /// Read errors are yielded as they happen, and polling continues afterwards.
+    pub fn watch_status(
+        &self,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<StatusChange, DeviceError>> + '_ {
+        stream::unfold((None, false), move |(mut last, mut polled)| async move {
+            loop {
+                if polled {
+                    tokio::time::sleep(interval).await;
+                }
+                polled = true;
+
+                let reading = match self.measurements().await {
+                    Ok(reading) => reading,
+                    Err(err) => return Some((Err(err), (last, polled))),
+                };
+
+                match last.replace(reading.status) {
+                    Some(from) if from != reading.status => {
+                        let change = StatusChange {
+                            from,
+                            to: reading.status,
+                            at: SystemTime::now(),
+                            reading,
+                        };
+
+                        return Some((Ok(change), (last, polled)));
+                    }
+                    _ => continue,
+                }
+            }
+        })
+    }
+}